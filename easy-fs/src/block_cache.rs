@@ -0,0 +1,170 @@
+//! Block cache: keeps recently touched disk blocks resident in memory
+
+use super::BlockDevice;
+use crate::BLOCK_SZ;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use lazy_static::*;
+use spin::Mutex;
+
+/// Default capacity of the global block cache manager
+const BLOCK_CACHE_SIZE: usize = 16;
+
+/// Cached content of a single disk block
+pub struct BlockCache {
+    cache: [u8; BLOCK_SZ],
+    block_id: usize,
+    block_device: Arc<dyn BlockDevice>,
+    modified: bool,
+}
+
+impl BlockCache {
+    /// Load a new BlockCache from disk
+    pub fn new(block_id: usize, block_device: Arc<dyn BlockDevice>) -> Self {
+        let mut cache = [0u8; BLOCK_SZ];
+        block_device.read_block(block_id, &mut cache);
+        Self {
+            cache,
+            block_id,
+            block_device,
+            modified: false,
+        }
+    }
+
+    fn addr_of_offset(&self, offset: usize) -> usize {
+        &self.cache[offset] as *const _ as usize
+    }
+
+    /// Get an immutable reference to the struct at `offset` in this block
+    pub fn get_ref<T>(&self, offset: usize) -> &T
+    where
+        T: Sized,
+    {
+        let type_size = core::mem::size_of::<T>();
+        assert!(offset + type_size <= BLOCK_SZ);
+        let addr = self.addr_of_offset(offset);
+        unsafe { &*(addr as *const T) }
+    }
+
+    /// Get a mutable reference to the struct at `offset` in this block
+    pub fn get_mut<T>(&mut self, offset: usize) -> &mut T
+    where
+        T: Sized,
+    {
+        let type_size = core::mem::size_of::<T>();
+        assert!(offset + type_size <= BLOCK_SZ);
+        self.modified = true;
+        let addr = self.addr_of_offset(offset);
+        unsafe { &mut *(addr as *mut T) }
+    }
+
+    /// Read the struct at `offset` via `f`
+    pub fn read<T, V>(&self, offset: usize, f: impl FnOnce(&T) -> V) -> V {
+        f(self.get_ref(offset))
+    }
+
+    /// Modify the struct at `offset` via `f`
+    pub fn modify<T, V>(&mut self, offset: usize, f: impl FnOnce(&mut T) -> V) -> V {
+        f(self.get_mut(offset))
+    }
+
+    /// Write this block back to disk if it has been modified
+    pub fn sync(&mut self) {
+        if self.modified {
+            self.modified = false;
+            self.block_device.write_block(self.block_id, &self.cache);
+        }
+    }
+}
+
+impl Drop for BlockCache {
+    fn drop(&mut self) {
+        self.sync();
+    }
+}
+
+/// An LRU-bounded manager of `BlockCache`s. The front of `queue` is the
+/// least-recently-used entry, the back is the most-recently-used.
+pub struct BlockCacheManager {
+    capacity: usize,
+    queue: VecDeque<(usize, Arc<Mutex<BlockCache>>)>,
+}
+
+impl BlockCacheManager {
+    /// Create an empty cache manager holding at most `capacity` blocks
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Get the cache entry for `block_id`, loading it from `block_device` if
+    /// absent, and move it to the most-recently-used position
+    pub fn get_block_cache(
+        &mut self,
+        block_id: usize,
+        block_device: Arc<dyn BlockDevice>,
+    ) -> Arc<Mutex<BlockCache>> {
+        if let Some(idx) = self.queue.iter().position(|pair| pair.0 == block_id) {
+            let pair = self.queue.remove(idx).unwrap();
+            let block_cache = Arc::clone(&pair.1);
+            self.queue.push_back(pair);
+            return block_cache;
+        }
+        if self.queue.len() == self.capacity {
+            self.evict_lru();
+        }
+        // load block into mem and push back as the most-recently-used entry
+        let block_cache = Arc::new(Mutex::new(BlockCache::new(
+            block_id,
+            Arc::clone(&block_device),
+        )));
+        self.queue.push_back((block_id, Arc::clone(&block_cache)));
+        block_cache
+    }
+
+    /// Evict the least-recently-used entry that is not held externally,
+    /// writing it back first if dirty. Entries still held by callers are
+    /// skipped in LRU order until one can be evicted.
+    fn evict_lru(&mut self) {
+        if let Some(idx) = self
+            .queue
+            .iter()
+            .position(|pair| Arc::strong_count(&pair.1) == 1)
+        {
+            let (_, cache) = self.queue.remove(idx).unwrap();
+            cache.lock().sync();
+        } else {
+            panic!("Run out of BlockCache!");
+        }
+    }
+
+    /// Write every dirty entry back to disk without evicting anything
+    pub fn flush(&self) {
+        for (_, cache) in self.queue.iter() {
+            cache.lock().sync();
+        }
+    }
+}
+
+lazy_static! {
+    /// The global block cache manager
+    pub static ref BLOCK_CACHE_MANAGER: Mutex<BlockCacheManager> =
+        Mutex::new(BlockCacheManager::new(BLOCK_CACHE_SIZE));
+}
+
+/// Get the cache entry for `block_id`, going through the global manager
+pub fn get_block_cache(
+    block_id: usize,
+    block_device: Arc<dyn BlockDevice>,
+) -> Arc<Mutex<BlockCache>> {
+    BLOCK_CACHE_MANAGER
+        .lock()
+        .get_block_cache(block_id, block_device)
+}
+
+/// Flush all dirty blocks in the cache back to disk
+pub fn block_cache_sync_all() {
+    BLOCK_CACHE_MANAGER.lock().flush();
+}
@@ -0,0 +1,11 @@
+//! Abstract block device interface
+
+use core::any::Any;
+
+/// Trait for a block device, implemented by the platform
+pub trait BlockDevice: Send + Sync + Any {
+    /// Read a block of data into `buf`
+    fn read_block(&self, block_id: usize, buf: &mut [u8]);
+    /// Write a block of data from `buf`
+    fn write_block(&self, block_id: usize, buf: &[u8]);
+}
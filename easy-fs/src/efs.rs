@@ -0,0 +1,192 @@
+//! The easy file system, gluing together the bitmaps, block cache and layout
+
+use super::{
+    block_cache_sync_all, get_block_cache, Bitmap, BlockDevice, DiskInode, DiskInodeType, BLOCK_SZ,
+};
+use alloc::sync::Arc;
+use spin::Mutex;
+
+/// The on-disk super block type, read from and written to block 0
+type SuperBlock = EfsSuperBlock;
+
+/// On-disk super block, occupying block 0
+#[repr(C)]
+pub struct EfsSuperBlock {
+    magic: u32,
+    pub total_blocks: u32,
+    pub inode_bitmap_blocks: u32,
+    pub inode_area_blocks: u32,
+    pub data_bitmap_blocks: u32,
+    pub data_area_blocks: u32,
+}
+
+const EFS_MAGIC: u32 = 0x3b800001;
+
+impl EfsSuperBlock {
+    fn initialize(
+        &mut self,
+        total_blocks: u32,
+        inode_bitmap_blocks: u32,
+        inode_area_blocks: u32,
+        data_bitmap_blocks: u32,
+        data_area_blocks: u32,
+    ) {
+        *self = Self {
+            magic: EFS_MAGIC,
+            total_blocks,
+            inode_bitmap_blocks,
+            inode_area_blocks,
+            data_bitmap_blocks,
+            data_area_blocks,
+        }
+    }
+    fn is_valid(&self) -> bool {
+        self.magic == EFS_MAGIC
+    }
+}
+
+/// The filesystem itself: owns the inode/data bitmaps and knows where each
+/// region of the disk begins
+pub struct EasyFileSystem {
+    /// Underlying block device
+    pub block_device: Arc<dyn BlockDevice>,
+    /// Inode bitmap
+    pub inode_bitmap: Bitmap,
+    /// Data bitmap
+    pub data_bitmap: Bitmap,
+    inode_area_start_block: u32,
+    data_area_start_block: u32,
+    /// Injected wall-clock source (seconds since epoch), since the fs core
+    /// is `no_std` and cannot call a platform clock directly
+    pub clock: fn() -> u64,
+}
+
+impl EasyFileSystem {
+    /// Format `block_device` into a fresh filesystem spanning `total_blocks`
+    pub fn create(
+        block_device: Arc<dyn BlockDevice>,
+        total_blocks: u32,
+        inode_bitmap_blocks: u32,
+        clock: fn() -> u64,
+    ) -> Arc<Mutex<Self>> {
+        // calculate block size of areas & create bitmaps
+        let inode_bitmap = Bitmap::new(1, inode_bitmap_blocks as usize);
+        let inode_num = inode_bitmap.maximum();
+        let inode_area_blocks =
+            ((inode_num * core::mem::size_of::<DiskInode>() + BLOCK_SZ - 1) / BLOCK_SZ) as u32;
+        let inode_total_blocks = inode_bitmap_blocks + inode_area_blocks;
+        let data_total_blocks = total_blocks - 1 - inode_total_blocks;
+        let data_bitmap_blocks = (data_total_blocks + 4096) / 4097;
+        let data_area_blocks = data_total_blocks - data_bitmap_blocks;
+        let data_bitmap = Bitmap::new(
+            (1 + inode_total_blocks) as usize,
+            data_bitmap_blocks as usize,
+        );
+        let mut efs = Self {
+            block_device: Arc::clone(&block_device),
+            inode_bitmap,
+            data_bitmap,
+            inode_area_start_block: 1 + inode_bitmap_blocks,
+            data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+            clock,
+        };
+        // clear every data block
+        for i in 0..total_blocks {
+            get_block_cache(i as usize, Arc::clone(&block_device))
+                .lock()
+                .modify(0, |data_block: &mut [u8; BLOCK_SZ]| {
+                    for byte in data_block.iter_mut() {
+                        *byte = 0;
+                    }
+                });
+        }
+        // initialize super block
+        get_block_cache(0, Arc::clone(&block_device)).lock().modify(
+            0,
+            |super_block: &mut SuperBlock| {
+                super_block.initialize(
+                    total_blocks,
+                    inode_bitmap_blocks,
+                    inode_area_blocks,
+                    data_bitmap_blocks,
+                    data_area_blocks,
+                );
+            },
+        );
+        // create the root directory
+        assert_eq!(efs.alloc_inode(), 0);
+        let (root_inode_block_id, root_inode_offset) = efs.get_disk_inode_pos(0);
+        get_block_cache(root_inode_block_id as usize, Arc::clone(&block_device))
+            .lock()
+            .modify(root_inode_offset, |disk_inode: &mut DiskInode| {
+                disk_inode.initialize(DiskInodeType::Directory);
+                disk_inode.touch_all(clock());
+            });
+        block_cache_sync_all();
+        Arc::new(Mutex::new(efs))
+    }
+
+    /// Open an existing filesystem from `block_device`
+    pub fn open(block_device: Arc<dyn BlockDevice>, clock: fn() -> u64) -> Arc<Mutex<Self>> {
+        let block_cache = get_block_cache(0, Arc::clone(&block_device));
+        let inner = block_cache.lock();
+        inner.read(0, |super_block: &SuperBlock| {
+            assert!(super_block.is_valid(), "Error loading efs!");
+            let inode_total_blocks =
+                super_block.inode_bitmap_blocks + super_block.inode_area_blocks;
+            let efs = Self {
+                block_device: Arc::clone(&block_device),
+                inode_bitmap: Bitmap::new(1, super_block.inode_bitmap_blocks as usize),
+                data_bitmap: Bitmap::new(
+                    (1 + inode_total_blocks) as usize,
+                    super_block.data_bitmap_blocks as usize,
+                ),
+                inode_area_start_block: 1 + super_block.inode_bitmap_blocks,
+                data_area_start_block: 1 + inode_total_blocks + super_block.data_bitmap_blocks,
+                clock,
+            };
+            Arc::new(Mutex::new(efs))
+        })
+    }
+
+    /// Allocate a new inode, returning its id
+    pub fn alloc_inode(&mut self) -> u32 {
+        self.inode_bitmap.alloc(&self.block_device).unwrap() as u32
+    }
+
+    /// Allocate a new data block, returning its block id
+    pub fn alloc_data(&mut self) -> u32 {
+        self.data_bitmap.alloc(&self.block_device).unwrap() as u32 + self.data_area_start_block
+    }
+
+    /// Free a data block allocated by `alloc_data`
+    pub fn dealloc_data(&mut self, block_id: u32) {
+        get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(0, |data_block: &mut [u8; BLOCK_SZ]| {
+                data_block.iter_mut().for_each(|p| {
+                    *p = 0;
+                })
+            });
+        self.data_bitmap.dealloc(
+            &self.block_device,
+            (block_id - self.data_area_start_block) as usize,
+        )
+    }
+
+    /// Resolve an inode id to its (block id, in-block offset)
+    pub fn get_disk_inode_pos(&self, inode_id: u32) -> (u32, usize) {
+        let inode_size = core::mem::size_of::<DiskInode>();
+        let inodes_per_block = (BLOCK_SZ / inode_size) as u32;
+        let block_id = self.inode_area_start_block + inode_id / inodes_per_block;
+        (
+            block_id,
+            (inode_id % inodes_per_block) as usize * inode_size,
+        )
+    }
+
+    /// Block id at which the inode area starts
+    pub fn get_inode_start_block_id(&self) -> u32 {
+        self.inode_area_start_block
+    }
+}
@@ -0,0 +1,667 @@
+//! On-disk data structures: inodes and directory entries
+
+use super::{get_block_cache, BlockDevice, BLOCK_SZ};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Number of direct block pointers held by a `DiskInode`
+const INODE_DIRECT_COUNT: usize = 27;
+
+/// Number of pointers held by a single indirect block
+const INODE_INDIRECT1_COUNT: usize = BLOCK_SZ / 4;
+
+const NAME_LENGTH_LIMIT: usize = 27;
+
+/// A block full of u32 pointers to other blocks
+type IndirectBlock = [u32; BLOCK_SZ / 4];
+/// A raw data block
+type DataBlock = [u8; BLOCK_SZ];
+
+/// Whether a `DiskInode` represents a regular file, a directory or a symbolic link
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum DiskInodeType {
+    /// Regular file
+    File,
+    /// Directory
+    Directory,
+    /// Symbolic link; its file data holds the UTF-8 target path
+    Symlink,
+}
+
+/// On-disk inode: metadata plus the block pointers making up a file's contents
+#[repr(C)]
+pub struct DiskInode {
+    /// Size in bytes of the underlying data
+    pub size: u32,
+    /// Direct block pointers
+    pub direct: [u32; INODE_DIRECT_COUNT],
+    /// Single-indirect block pointer
+    pub indirect1: u32,
+    /// Double-indirect block pointer
+    pub indirect2: u32,
+    /// Triple-indirect block pointer
+    pub indirect3: u32,
+    /// File, directory or symbolic link
+    type_: DiskInodeType,
+    /// Hard link (reference) count
+    nlink: u32,
+    /// Owning user id
+    pub uid: u32,
+    /// Owning group id
+    pub gid: u32,
+    /// Permission bits: low 9 bits rwx for owner/group/other, plus setuid/setgid/sticky
+    pub mode: u16,
+    /// Last access time, in seconds since the epoch
+    pub atime: u64,
+    /// Last content modification time, in seconds since the epoch
+    pub mtime: u64,
+    /// Last metadata change time, in seconds since the epoch
+    pub ctime: u64,
+}
+
+/// Requested access bits for `DiskInode::check_access`
+pub mod access_mask {
+    /// Read permission requested
+    pub const R: u8 = 0b100;
+    /// Write permission requested
+    pub const W: u8 = 0b010;
+    /// Execute permission requested
+    pub const X: u8 = 0b001;
+}
+
+/// setuid bit within `DiskInode::mode`
+pub const S_ISUID: u16 = 0o4000;
+/// setgid bit within `DiskInode::mode`
+pub const S_ISGID: u16 = 0o2000;
+/// sticky bit within `DiskInode::mode`
+pub const S_ISVTX: u16 = 0o1000;
+
+impl DiskInode {
+    /// Initialize a freshly allocated disk inode as a file, directory or symbolic link
+    pub fn initialize(&mut self, type_: DiskInodeType) {
+        self.size = 0;
+        self.direct = [0; INODE_DIRECT_COUNT];
+        self.indirect1 = 0;
+        self.indirect2 = 0;
+        self.indirect3 = 0;
+        self.type_ = type_;
+        self.nlink = 1;
+        self.uid = 0;
+        self.gid = 0;
+        self.mode = match type_ {
+            DiskInodeType::File => 0o644,
+            DiskInodeType::Directory => 0o755,
+            DiskInodeType::Symlink => 0o777,
+        };
+        self.atime = 0;
+        self.mtime = 0;
+        self.ctime = 0;
+    }
+
+    /// Record that the file's contents were read at time `now`
+    pub fn touch_atime(&mut self, now: u64) {
+        self.atime = now;
+    }
+    /// Record that the file's contents were changed at time `now`
+    pub fn touch_mtime(&mut self, now: u64) {
+        self.mtime = now;
+        self.ctime = now;
+    }
+    /// Record that the file's metadata was changed at time `now`
+    pub fn touch_ctime(&mut self, now: u64) {
+        self.ctime = now;
+    }
+    /// Stamp all three timestamps with `now`, for freshly created inodes
+    pub fn touch_all(&mut self, now: u64) {
+        self.atime = now;
+        self.mtime = now;
+        self.ctime = now;
+    }
+
+    /// Whether this inode is a directory
+    pub fn is_dir(&self) -> bool {
+        self.type_ == DiskInodeType::Directory
+    }
+    /// Whether this inode is a regular file
+    pub fn is_file(&self) -> bool {
+        self.type_ == DiskInodeType::File
+    }
+    /// Whether this inode is a symbolic link
+    pub fn is_symlink(&self) -> bool {
+        self.type_ == DiskInodeType::Symlink
+    }
+    /// This inode's type
+    pub fn get_type(&self) -> DiskInodeType {
+        self.type_
+    }
+
+    /// Bump the reference (hard link) count
+    pub fn refthis(&mut self) {
+        self.nlink += 1;
+    }
+    /// Drop one reference; returns whether the inode is still alive
+    pub fn unref(&mut self) -> bool {
+        assert!(self.nlink > 0);
+        self.nlink -= 1;
+        self.nlink > 0
+    }
+    /// Current reference count
+    pub fn get_ref_count(&self) -> u32 {
+        self.nlink
+    }
+
+    /// Standard Unix access check: does (uid, gid, groups) have all the
+    /// permissions in `mask` on this inode?
+    pub fn check_access(&self, uid: u32, gid: u32, groups: &[u32], mask: u8) -> bool {
+        let mask = mask & 0b111;
+        if uid == 0 {
+            // root: read/write always granted, execute only if someone may execute
+            let exec_ok = (self.mode & 0o111) != 0;
+            return (mask & access_mask::X == 0) || exec_ok;
+        }
+        let perm_bits = if uid == self.uid {
+            (self.mode >> 6) & 0o7
+        } else if gid == self.gid || groups.contains(&self.gid) {
+            (self.mode >> 3) & 0o7
+        } else {
+            self.mode & 0o7
+        };
+        (perm_bits as u8) & mask == mask
+    }
+
+    /// Strip the setuid bit (and setgid, if group-execute is set) after a
+    /// non-root write to the file's contents
+    pub fn clear_suid_sgid(&mut self) {
+        self.mode &= !S_ISUID;
+        if self.mode & 0o010 != 0 {
+            self.mode &= !S_ISGID;
+        }
+    }
+
+    fn _pointer_at(block_id: u32, inner_id: usize, block_device: &Arc<dyn BlockDevice>) -> u32 {
+        get_block_cache(block_id as usize, Arc::clone(block_device))
+            .lock()
+            .read(0, |indirect_block: &IndirectBlock| indirect_block[inner_id])
+    }
+
+    /// Translate a logical block index into a physical block id, descending
+    /// through direct, single-, double- or triple-indirect pointers as needed
+    pub fn get_block_id(&self, inner_id: u32, block_device: &Arc<dyn BlockDevice>) -> u32 {
+        let mut inner_id = inner_id as usize;
+        if inner_id < INODE_DIRECT_COUNT {
+            return self.direct[inner_id];
+        }
+        inner_id -= INODE_DIRECT_COUNT;
+        if inner_id < INODE_INDIRECT1_COUNT {
+            return Self::_pointer_at(self.indirect1, inner_id, block_device);
+        }
+        inner_id -= INODE_INDIRECT1_COUNT;
+        let l2_cap = INODE_INDIRECT1_COUNT * INODE_INDIRECT1_COUNT;
+        if inner_id < l2_cap {
+            let l1_block = Self::_pointer_at(self.indirect2, inner_id / INODE_INDIRECT1_COUNT, block_device);
+            return Self::_pointer_at(l1_block, inner_id % INODE_INDIRECT1_COUNT, block_device);
+        }
+        inner_id -= l2_cap;
+        let l2_block = Self::_pointer_at(self.indirect3, inner_id / l2_cap, block_device);
+        let rem = inner_id % l2_cap;
+        let l1_block = Self::_pointer_at(l2_block, rem / INODE_INDIRECT1_COUNT, block_device);
+        Self::_pointer_at(l1_block, rem % INODE_INDIRECT1_COUNT, block_device)
+    }
+
+    /// Number of data blocks required to hold `size` bytes
+    pub fn data_blocks(size: u32) -> u32 {
+        Self::_data_blocks(size)
+    }
+    fn _data_blocks(size: u32) -> u32 {
+        (size as usize + BLOCK_SZ - 1) as u32 / BLOCK_SZ as u32
+    }
+
+    /// Number of pointer blocks needed to address `n` data blocks through one
+    /// level of indirection of the given `depth` (1 = single, 2 = double, 3 =
+    /// triple indirect)
+    fn pointer_blocks_needed(n: usize, depth: usize) -> usize {
+        if n == 0 {
+            return 0;
+        }
+        if depth == 1 {
+            return 1;
+        }
+        let child_capacity = INODE_INDIRECT1_COUNT.pow(depth as u32 - 1);
+        let children = (n + child_capacity - 1) / child_capacity;
+        let mut total = 1; // this level's own pointer block
+        for i in 0..children {
+            let child_n = if i + 1 == children {
+                n - i * child_capacity
+            } else {
+                child_capacity
+            };
+            total += Self::pointer_blocks_needed(child_n, depth - 1);
+        }
+        total
+    }
+
+    /// Total blocks (data + index) needed to hold `size` bytes
+    pub fn total_blocks(size: u32) -> u32 {
+        let data_blocks = Self::_data_blocks(size) as usize;
+        let mut total = data_blocks;
+        let mut remaining = data_blocks.saturating_sub(INODE_DIRECT_COUNT);
+        if remaining == 0 {
+            return total as u32;
+        }
+        let l1_cap = INODE_INDIRECT1_COUNT;
+        let l2_cap = l1_cap * l1_cap;
+        if remaining <= l1_cap {
+            total += Self::pointer_blocks_needed(remaining, 1);
+            return total as u32;
+        }
+        total += Self::pointer_blocks_needed(l1_cap, 1);
+        remaining -= l1_cap;
+        if remaining <= l2_cap {
+            total += Self::pointer_blocks_needed(remaining, 2);
+            return total as u32;
+        }
+        total += Self::pointer_blocks_needed(l2_cap, 2);
+        remaining -= l2_cap;
+        total += Self::pointer_blocks_needed(remaining, 3);
+        total as u32
+    }
+    /// Additional blocks needed to grow this inode up to `new_size` bytes
+    pub fn blocks_num_needed(&self, new_size: u32) -> u32 {
+        assert!(new_size >= self.size);
+        Self::total_blocks(new_size) - Self::total_blocks(self.size)
+    }
+
+    /// Fill data-block pointers for indices `[lo, hi)` reachable through
+    /// pointer block `block_id`, whose entries address subtrees of `depth`
+    /// (1 = entries point directly at data blocks). Intermediate pointer
+    /// blocks are allocated lazily, the first time a given child is touched.
+    fn fill(
+        block_id: u32,
+        lo: usize,
+        hi: usize,
+        depth: usize,
+        new_blocks: &mut impl Iterator<Item = u32>,
+        block_device: &Arc<dyn BlockDevice>,
+    ) {
+        if lo >= hi {
+            return;
+        }
+        if depth == 1 {
+            get_block_cache(block_id as usize, Arc::clone(block_device))
+                .lock()
+                .modify(0, |blk: &mut IndirectBlock| {
+                    for slot in blk.iter_mut().take(hi).skip(lo) {
+                        *slot = new_blocks.next().unwrap();
+                    }
+                });
+            return;
+        }
+        let child_capacity = INODE_INDIRECT1_COUNT.pow(depth as u32 - 1);
+        let lo_child = lo / child_capacity;
+        let hi_child = (hi + child_capacity - 1) / child_capacity;
+        for child in lo_child..hi_child {
+            let child_lo = if child == lo_child { lo % child_capacity } else { 0 };
+            let child_hi = if child + 1 == hi_child {
+                (hi - child * child_capacity).min(child_capacity)
+            } else {
+                child_capacity
+            };
+            let child_block_id =
+                get_block_cache(block_id as usize, Arc::clone(block_device))
+                    .lock()
+                    .read(0, |blk: &IndirectBlock| blk[child]);
+            let child_block_id = if child_block_id == 0 {
+                let id = new_blocks.next().unwrap();
+                get_block_cache(block_id as usize, Arc::clone(block_device))
+                    .lock()
+                    .modify(0, |blk: &mut IndirectBlock| blk[child] = id);
+                id
+            } else {
+                child_block_id
+            };
+            Self::fill(child_block_id, child_lo, child_hi, depth - 1, new_blocks, block_device);
+        }
+    }
+
+    /// Grow this inode to `new_size`, consuming freshly allocated `new_blocks`
+    pub fn increase_size(
+        &mut self,
+        new_size: u32,
+        new_blocks: Vec<u32>,
+        block_device: &Arc<dyn BlockDevice>,
+    ) {
+        let mut current_blocks = Self::_data_blocks(self.size) as usize;
+        self.size = new_size;
+        let total_blocks = Self::_data_blocks(self.size) as usize;
+        let mut new_blocks = new_blocks.into_iter();
+
+        // direct blocks
+        while current_blocks < total_blocks.min(INODE_DIRECT_COUNT) {
+            self.direct[current_blocks] = new_blocks.next().unwrap();
+            current_blocks += 1;
+        }
+        if total_blocks <= INODE_DIRECT_COUNT {
+            return;
+        }
+
+        let l1_cap = INODE_INDIRECT1_COUNT;
+        let l2_cap = l1_cap * l1_cap;
+
+        // single-indirect range: [INODE_DIRECT_COUNT, INODE_DIRECT_COUNT + l1_cap)
+        let lo = current_blocks.saturating_sub(INODE_DIRECT_COUNT);
+        let hi = (total_blocks - INODE_DIRECT_COUNT).min(l1_cap);
+        if hi > lo {
+            if self.indirect1 == 0 {
+                self.indirect1 = new_blocks.next().unwrap();
+            }
+            Self::fill(self.indirect1, lo, hi, 1, &mut new_blocks, block_device);
+        }
+        current_blocks = current_blocks.max(INODE_DIRECT_COUNT + hi);
+        if total_blocks <= INODE_DIRECT_COUNT + l1_cap {
+            return;
+        }
+
+        // double-indirect range
+        let base2 = INODE_DIRECT_COUNT + l1_cap;
+        let lo = current_blocks.saturating_sub(base2);
+        let hi = (total_blocks - base2).min(l2_cap);
+        if hi > lo {
+            if self.indirect2 == 0 {
+                self.indirect2 = new_blocks.next().unwrap();
+            }
+            Self::fill(self.indirect2, lo, hi, 2, &mut new_blocks, block_device);
+        }
+        current_blocks = current_blocks.max(base2 + hi);
+        if total_blocks <= base2 + l2_cap {
+            return;
+        }
+
+        // triple-indirect range
+        let base3 = base2 + l2_cap;
+        let lo = current_blocks.saturating_sub(base3);
+        let hi = total_blocks - base3;
+        if self.indirect3 == 0 {
+            self.indirect3 = new_blocks.next().unwrap();
+        }
+        Self::fill(self.indirect3, lo, hi, 3, &mut new_blocks, block_device);
+    }
+
+    /// Free data-block pointers for indices `[lo, hi)` reachable through
+    /// pointer block `block_id`, pushing the freed block ids onto `v`. Any
+    /// child pointer block that becomes entirely empty (its local range
+    /// starts at 0) is freed too.
+    fn drain(
+        block_id: u32,
+        lo: usize,
+        hi: usize,
+        depth: usize,
+        v: &mut Vec<u32>,
+        block_device: &Arc<dyn BlockDevice>,
+    ) {
+        if lo >= hi {
+            return;
+        }
+        if depth == 1 {
+            get_block_cache(block_id as usize, Arc::clone(block_device))
+                .lock()
+                .modify(0, |blk: &mut IndirectBlock| {
+                    for slot in blk.iter_mut().take(hi).skip(lo) {
+                        v.push(*slot);
+                        *slot = 0;
+                    }
+                });
+            return;
+        }
+        let child_capacity = INODE_INDIRECT1_COUNT.pow(depth as u32 - 1);
+        let lo_child = lo / child_capacity;
+        let hi_child = (hi + child_capacity - 1) / child_capacity;
+        for child in (lo_child..hi_child).rev() {
+            let child_lo = if child == lo_child { lo % child_capacity } else { 0 };
+            let child_hi = if child + 1 == hi_child {
+                (hi - child * child_capacity).min(child_capacity)
+            } else {
+                child_capacity
+            };
+            let child_block_id =
+                get_block_cache(block_id as usize, Arc::clone(block_device))
+                    .lock()
+                    .read(0, |blk: &IndirectBlock| blk[child]);
+            Self::drain(child_block_id, child_lo, child_hi, depth - 1, v, block_device);
+            if child_lo == 0 {
+                v.push(child_block_id);
+                get_block_cache(block_id as usize, Arc::clone(block_device))
+                    .lock()
+                    .modify(0, |blk: &mut IndirectBlock| blk[child] = 0);
+            }
+        }
+    }
+
+    /// Shrink this inode down to `new_size`, returning the data and pointer
+    /// blocks that are no longer referenced so the caller can free them
+    pub fn dealloc_to(&mut self, new_size: u32, block_device: &Arc<dyn BlockDevice>) -> Vec<u32> {
+        assert!(new_size <= self.size);
+        let mut v: Vec<u32> = Vec::new();
+        let old_blocks = Self::_data_blocks(self.size) as usize;
+        self.size = new_size;
+        let new_blocks = Self::_data_blocks(self.size) as usize;
+
+        let l1_cap = INODE_INDIRECT1_COUNT;
+        let l2_cap = l1_cap * l1_cap;
+        let base2 = INODE_DIRECT_COUNT + l1_cap;
+        let base3 = base2 + l2_cap;
+
+        // triple-indirect
+        if old_blocks > base3 {
+            let hi = old_blocks - base3;
+            let lo = new_blocks.saturating_sub(base3).min(hi);
+            Self::drain(self.indirect3, lo, hi, 3, &mut v, block_device);
+            if lo == 0 {
+                v.push(self.indirect3);
+                self.indirect3 = 0;
+            }
+        }
+        // double-indirect
+        if old_blocks > base2 {
+            let hi = (old_blocks - base2).min(l2_cap);
+            let lo = new_blocks.saturating_sub(base2).min(hi);
+            Self::drain(self.indirect2, lo, hi, 2, &mut v, block_device);
+            if lo == 0 {
+                v.push(self.indirect2);
+                self.indirect2 = 0;
+            }
+        }
+        // single-indirect
+        if old_blocks > INODE_DIRECT_COUNT {
+            let hi = (old_blocks - INODE_DIRECT_COUNT).min(l1_cap);
+            let lo = new_blocks.saturating_sub(INODE_DIRECT_COUNT).min(hi);
+            Self::drain(self.indirect1, lo, hi, 1, &mut v, block_device);
+            if lo == 0 {
+                v.push(self.indirect1);
+                self.indirect1 = 0;
+            }
+        }
+        // direct
+        {
+            let hi = old_blocks.min(INODE_DIRECT_COUNT);
+            let lo = new_blocks.min(hi);
+            for i in (lo..hi).rev() {
+                v.push(self.direct[i]);
+                self.direct[i] = 0;
+            }
+        }
+        v
+    }
+
+    /// Deallocate every data and index block and return them, resetting size to zero
+    pub fn clear_size(&mut self, block_device: &Arc<dyn BlockDevice>) -> Vec<u32> {
+        self.dealloc_to(0, block_device)
+    }
+
+    fn _read_write_range(&self, offset: usize, buf_len: usize) -> (usize, usize) {
+        let size = self.size as usize;
+        let start = offset.min(size);
+        let end = (offset + buf_len).min(size);
+        (start, end)
+    }
+
+    /// Read up to `buf.len()` bytes starting at `offset`
+    pub fn read_at(
+        &self,
+        offset: usize,
+        buf: &mut [u8],
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> usize {
+        let (start, end) = self._read_write_range(offset, buf.len());
+        if start >= end {
+            return 0;
+        }
+        let mut start_block = start / BLOCK_SZ;
+        let mut read_size = 0usize;
+        let mut cur = start;
+        loop {
+            let mut end_cur = (cur / BLOCK_SZ + 1) * BLOCK_SZ;
+            end_cur = end_cur.min(end);
+            let block_read_size = end_cur - cur;
+            let dst = &mut buf[read_size..read_size + block_read_size];
+            get_block_cache(
+                self.get_block_id(start_block as u32, block_device) as usize,
+                Arc::clone(block_device),
+            )
+            .lock()
+            .read(0, |data_block: &DataBlock| {
+                let start_in_block = cur % BLOCK_SZ;
+                dst.copy_from_slice(&data_block[start_in_block..start_in_block + block_read_size]);
+            });
+            read_size += block_read_size;
+            if end_cur == end {
+                break;
+            }
+            start_block += 1;
+            cur = end_cur;
+        }
+        read_size
+    }
+
+    /// Write up to `buf.len()` bytes starting at `offset`. Assumes the inode
+    /// was already grown to cover `offset + buf.len()`.
+    pub fn write_at(
+        &mut self,
+        offset: usize,
+        buf: &[u8],
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> usize {
+        let (start, end) = self._read_write_range(offset, buf.len());
+        assert!(start <= end);
+        if start >= end {
+            return 0;
+        }
+        let mut start_block = start / BLOCK_SZ;
+        let mut write_size = 0usize;
+        let mut cur = start;
+        loop {
+            let mut end_cur = (cur / BLOCK_SZ + 1) * BLOCK_SZ;
+            end_cur = end_cur.min(end);
+            let block_write_size = end_cur - cur;
+            get_block_cache(
+                self.get_block_id(start_block as u32, block_device) as usize,
+                Arc::clone(block_device),
+            )
+            .lock()
+            .modify(0, |data_block: &mut DataBlock| {
+                let src = &buf[write_size..write_size + block_write_size];
+                let start_in_block = cur % BLOCK_SZ;
+                data_block[start_in_block..start_in_block + block_write_size].copy_from_slice(src);
+            });
+            write_size += block_write_size;
+            if end_cur == end {
+                break;
+            }
+            start_block += 1;
+            cur = end_cur;
+        }
+        write_size
+    }
+}
+
+/// A POSIX-like stat() result, carrying everything needed to implement a
+/// kernel `stat`/`fstat` syscall
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FileStat {
+    /// Inode number
+    pub ino: u64,
+    /// Whether this inode is a directory; `false` for both regular files and symbolic links
+    pub is_dir: bool,
+    /// Size in bytes
+    pub size: u64,
+    /// Hard link count
+    pub nlink: u32,
+    /// Permission bits
+    pub mode: u16,
+    /// Owning user id
+    pub uid: u32,
+    /// Owning group id
+    pub gid: u32,
+    /// Last access time
+    pub atime: u64,
+    /// Last content modification time
+    pub mtime: u64,
+    /// Last metadata change time
+    pub ctime: u64,
+}
+
+/// Size in bytes of an on-disk directory entry
+pub const DIRENT_SZ: usize = 32;
+
+/// A fixed-size on-disk directory entry: a name plus the inode it names
+#[repr(C)]
+pub struct DirEntry {
+    name: [u8; NAME_LENGTH_LIMIT + 1],
+    inode_number: u32,
+}
+
+impl DirEntry {
+    /// An all-zero directory entry
+    pub fn empty() -> Self {
+        Self {
+            name: [0u8; NAME_LENGTH_LIMIT + 1],
+            inode_number: 0,
+        }
+    }
+
+    /// Build a directory entry naming `inode_id`
+    pub fn new(name: &str, inode_id: u32) -> Self {
+        let mut bytes = [0u8; NAME_LENGTH_LIMIT + 1];
+        let name_bytes = name.as_bytes();
+        bytes[..name_bytes.len()].copy_from_slice(name_bytes);
+        Self {
+            name: bytes,
+            inode_number: inode_id,
+        }
+    }
+
+    /// View this entry as a byte slice, the source for `DiskInode::write_at`
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts(self as *const _ as usize as *const u8, DIRENT_SZ)
+        }
+    }
+    /// View this entry as a mutable byte slice, the destination for `DiskInode::read_at`
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        unsafe {
+            core::slice::from_raw_parts_mut(self as *mut _ as usize as *mut u8, DIRENT_SZ)
+        }
+    }
+
+    /// The entry's name, trimmed at the first NUL
+    pub fn name(&self) -> &str {
+        let len = (0usize..self.name.len())
+            .find(|&i| self.name[i] == 0)
+            .unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).unwrap()
+    }
+    /// The inode this entry names
+    pub fn inode_id(&self) -> u32 {
+        self.inode_number
+    }
+}
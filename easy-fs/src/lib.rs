@@ -0,0 +1,24 @@
+#![no_std]
+//! An easy file system isolated from the kernel
+
+extern crate alloc;
+
+mod bitmap;
+mod block_cache;
+mod block_dev;
+mod efs;
+mod layout;
+mod vfs;
+
+/// Size in bytes of a disk block
+pub const BLOCK_SZ: usize = 512;
+
+use bitmap::Bitmap;
+use block_cache::{block_cache_sync_all, get_block_cache};
+pub use block_dev::BlockDevice;
+pub use efs::EasyFileSystem;
+pub use layout::{
+    access_mask, DirEntry, DiskInode, DiskInodeType, FileStat, DIRENT_SZ, S_ISGID, S_ISUID,
+    S_ISVTX,
+};
+pub use vfs::Inode;
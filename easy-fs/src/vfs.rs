@@ -2,12 +2,16 @@ use crate::BLOCK_SZ;
 
 use super::{
     block_cache_sync_all, get_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType,
-    EasyFileSystem, DIRENT_SZ,
+    EasyFileSystem, FileStat, DIRENT_SZ,
 };
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use spin::{Mutex, MutexGuard};
+
+/// Maximum number of symlinks followed while resolving one path, to guard
+/// against cycles
+const MAX_SYMLINK_FOLLOWS: usize = 40;
 /// Virtual filesystem layer over easy-fs
 pub struct Inode {
     block_id: usize,
@@ -107,6 +111,7 @@ impl Inode {
             v.push(fs.alloc_data());
         }
         disk_inode.increase_size(new_size, v, &self.block_device);
+        disk_inode.touch_mtime((fs.clock)());
     }
     fn decrease_size(&self, new_size: u32, disk_inode: &mut DiskInode, fs: &mut MutexGuard<EasyFileSystem>)
     {
@@ -117,6 +122,7 @@ impl Inode {
         for block_id in v {
             fs.dealloc_data(block_id);
         }
+        disk_inode.touch_mtime((fs.clock)());
     }
     /// Create inode under current inode by name
     pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
@@ -139,6 +145,7 @@ impl Inode {
             .lock()
             .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
                 new_inode.initialize(DiskInodeType::File);
+                new_inode.touch_all((fs.clock)());
             });
         self.modify_disk_inode(|root_inode| {
             // append file in the dirent
@@ -167,6 +174,192 @@ impl Inode {
         // release efs lock automatically by compiler
     }
 
+    /// Create a subdirectory under current inode by name, wiring up `.` and
+    /// `..` dirents and bumping this inode's link count for the child's `..`
+    pub fn mkdir(&self, name: &str) -> Option<Arc<Inode>> {
+        let mut fs = self.fs.lock();
+        let op = |root_inode: &DiskInode| {
+            // assert it is a directory
+            assert!(root_inode.is_dir());
+            // has the file been created?
+            self.find_inode_id(name, root_inode)
+        };
+        if self.read_disk_inode(op).is_some() {
+            return None;
+        }
+        let self_inode_id = self._get_id_impl(&fs) as u32;
+        // alloc a inode for the new directory
+        let new_inode_id = fs.alloc_inode();
+        let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
+        get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
+                new_inode.initialize(DiskInodeType::Directory);
+                new_inode.touch_all((fs.clock)());
+            });
+        let new_inode = Self::new(
+            new_inode_block_id,
+            new_inode_block_offset,
+            self.fs.clone(),
+            self.block_device.clone(),
+        );
+        // write `.` and `..` dirents into the new directory
+        new_inode.modify_disk_inode(|dir_inode| {
+            new_inode.increase_size(2 * DIRENT_SZ as u32, dir_inode, &mut fs);
+            let dot = DirEntry::new(".", new_inode_id);
+            let dotdot = DirEntry::new("..", self_inode_id);
+            dir_inode.write_at(0, dot.as_bytes(), &self.block_device);
+            dir_inode.write_at(DIRENT_SZ, dotdot.as_bytes(), &self.block_device);
+        });
+        // append the new directory's dirent in this directory, and account
+        // for the child's `..` pointing back at us
+        self.modify_disk_inode(|root_inode| {
+            let file_count = (root_inode.size as usize) / DIRENT_SZ;
+            let new_size = (file_count + 1) * DIRENT_SZ;
+            self.increase_size(new_size as u32, root_inode, &mut fs);
+            let dirent = DirEntry::new(name, new_inode_id);
+            root_inode.write_at(
+                file_count * DIRENT_SZ,
+                dirent.as_bytes(),
+                &self.block_device,
+            );
+            root_inode.refthis();
+        });
+        block_cache_sync_all();
+        Some(Arc::new(new_inode))
+    }
+
+    /// Create a symbolic link under current inode by name, storing `target`
+    /// verbatim as the new inode's file contents
+    pub fn symlink(&self, name: &str, target: &str) -> Option<Arc<Inode>> {
+        let mut fs = self.fs.lock();
+        let op = |root_inode: &DiskInode| {
+            // assert it is a directory
+            assert!(root_inode.is_dir());
+            // has the file been created?
+            self.find_inode_id(name, root_inode)
+        };
+        if self.read_disk_inode(op).is_some() {
+            return None;
+        }
+        let new_inode_id = fs.alloc_inode();
+        let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
+        get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
+                new_inode.initialize(DiskInodeType::Symlink);
+                new_inode.touch_all((fs.clock)());
+            });
+        let new_inode = Self::new(
+            new_inode_block_id,
+            new_inode_block_offset,
+            self.fs.clone(),
+            self.block_device.clone(),
+        );
+        new_inode.modify_disk_inode(|link_inode| {
+            let target_bytes = target.as_bytes();
+            new_inode.increase_size(target_bytes.len() as u32, link_inode, &mut fs);
+            link_inode.write_at(0, target_bytes, &self.block_device);
+        });
+        self.modify_disk_inode(|root_inode| {
+            let file_count = (root_inode.size as usize) / DIRENT_SZ;
+            let new_size = (file_count + 1) * DIRENT_SZ;
+            self.increase_size(new_size as u32, root_inode, &mut fs);
+            let dirent = DirEntry::new(name, new_inode_id);
+            root_inode.write_at(
+                file_count * DIRENT_SZ,
+                dirent.as_bytes(),
+                &self.block_device,
+            );
+        });
+        block_cache_sync_all();
+        Some(Arc::new(new_inode))
+    }
+
+    /// Read the target path stored in this inode, if it is a symbolic link
+    pub fn read_link(&self) -> Option<String> {
+        if !self.read_disk_inode(|di| di.is_symlink()) {
+            return None;
+        }
+        let size = self.read_disk_inode(|di| di.size as usize);
+        let mut buf = Vec::with_capacity(size);
+        buf.resize(size, 0u8);
+        self.read_disk_inode(|di| di.read_at(0, &mut buf, &self.block_device));
+        String::from_utf8(buf).ok()
+    }
+
+    /// Resolve a `/`-separated path by walking each non-empty component
+    /// through `find`, returning the terminal inode or `None` if any
+    /// component is missing. When `follow_symlinks` is set, any symlink
+    /// encountered along the way (including a terminal one) is transparently
+    /// followed relative to the directory it was found in, giving up after
+    /// `MAX_SYMLINK_FOLLOWS` hops to guard against cycles.
+    pub fn find_path(&self, path: &str, follow_symlinks: bool) -> Option<Arc<Inode>> {
+        let mut hops = 0usize;
+        self.find_path_impl(path, follow_symlinks, &mut hops)
+    }
+    fn find_path_impl(
+        &self,
+        path: &str,
+        follow_symlinks: bool,
+        hops: &mut usize,
+    ) -> Option<Arc<Inode>> {
+        let mut components = path.split('/').filter(|s| !s.is_empty());
+        let mut dir = Arc::new(Self::new(
+            self.block_id as u32,
+            self.block_offset,
+            self.fs.clone(),
+            self.block_device.clone(),
+        ));
+        let mut cur = dir.find(components.next()?)?;
+        for component in components {
+            if follow_symlinks && cur.get_type() == DiskInodeType::Symlink {
+                cur = cur.follow_symlink(&dir, hops)?;
+            }
+            dir = cur.clone();
+            cur = cur.find(component)?;
+        }
+        if follow_symlinks && cur.get_type() == DiskInodeType::Symlink {
+            cur = cur.follow_symlink(&dir, hops)?;
+        }
+        Some(cur)
+    }
+    /// Follow this symlink's target path, resolved relative to `dir`
+    fn follow_symlink(&self, dir: &Arc<Inode>, hops: &mut usize) -> Option<Arc<Inode>> {
+        *hops += 1;
+        if *hops > MAX_SYMLINK_FOLLOWS {
+            return None;
+        }
+        let target = self.read_link()?;
+        dir.find_path_impl(&target, true, hops)
+    }
+
+    /// Remove an empty subdirectory named `name`. Refuses to remove a
+    /// directory that still contains entries other than `.`/`..`, or a name
+    /// that does not resolve to a directory.
+    pub fn rmdir(&self, name: &str) -> Result<(), &'static str> {
+        assert!(self.is_dir_file().0);
+        let mut target = self.find(name).ok_or("No such file or directory")?;
+        if !target.is_dir_file().0 {
+            return Err("Not a directory");
+        }
+        let entry_count = target.read_disk_inode(|di| (di.size as usize) / DIRENT_SZ);
+        if entry_count > 2 {
+            return Err("Directory not empty");
+        }
+        self.hard_unlink(&mut target, |_| {})?;
+        // Undo the nlink bump `mkdir` gave us on the child's behalf for its
+        // `..` pointing back at us -- without this, every rmdir leaks one
+        // reference on the parent. hard_unlink already synced the block
+        // cache for the child-side changes above, so only this one needs
+        // its own sync.
+        self.modify_disk_inode(|self_inode| {
+            self_inode.unref();
+        });
+        block_cache_sync_all();
+        Ok(())
+    }
+
     /// Make a hard link of file `From` to `To`.
     /// This operation increases reference count of file `From`.
     pub fn hard_link(&self, from: &mut Arc<Inode>, to_name: &str)-> Result<Arc<Inode>, &'static str> {
@@ -175,7 +368,9 @@ impl Inode {
             return Err("Link name is existed file");
         }
         let from_id = from.get_id();
-        from.modify_disk_inode(|fdi| { fdi.refthis(); });
+        let clock = self.fs.lock().clock;
+        let now = clock();
+        from.modify_disk_inode(|fdi| { fdi.refthis(); fdi.touch_ctime(now); });
         self.modify_disk_inode(|dir| {
             let old_size = dir.size;
             let _file_cnt = old_size / DIRENT_SZ as u32;
@@ -197,10 +392,12 @@ impl Inode {
         if file.get_ref_count() == 0 {
             return Err("Inode double free");
         }
-        let alive = file.modify_disk_inode(|di| { di.unref() });
+        let clock = self.fs.lock().clock;
+        let now = clock();
+        let alive = file.modify_disk_inode(|di| { di.touch_ctime(now); di.unref() });
         _printf("disk inode unref");
         if !alive {
-            // file.clear();
+            file.clear();
             _printf("clear content");
             let file_inode_id = file.get_id() as u32;
             _printf("file inode id");
@@ -250,19 +447,49 @@ impl Inode {
     }
     /// Read data from current inode
     pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
-        let _fs = self.fs.lock();
-        self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
+        let fs = self.fs.lock();
+        let now = (fs.clock)();
+        let read = self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device));
+        self.modify_disk_inode(|disk_inode| disk_inode.touch_atime(now));
+        read
     }
-    /// Write data to current inode
-    pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+    /// Write data to current inode, as user `writer_uid`.
+    ///
+    /// Strips the setuid bit (and the setgid bit, if group-execute is set)
+    /// whenever someone other than root modifies the file's contents.
+    pub fn write_at(&self, offset: usize, buf: &[u8], writer_uid: u32) -> usize {
         let mut fs = self.fs.lock();
+        let now = (fs.clock)();
         let size = self.modify_disk_inode(|disk_inode| {
             self.increase_size((offset + buf.len()) as u32, disk_inode, &mut fs);
-            disk_inode.write_at(offset, buf, &self.block_device)
+            let written = disk_inode.write_at(offset, buf, &self.block_device);
+            disk_inode.touch_mtime(now);
+            if writer_uid != 0 {
+                disk_inode.clear_suid_sgid();
+            }
+            written
         });
         block_cache_sync_all();
         size
     }
+    /// Change this inode's permission bits
+    pub fn chmod(&self, mode: u16) {
+        self.modify_disk_inode(|disk_inode| disk_inode.mode = mode & 0o7777);
+        block_cache_sync_all();
+    }
+    /// Change this inode's owning user and group
+    pub fn chown(&self, uid: u32, gid: u32) {
+        self.modify_disk_inode(|disk_inode| {
+            disk_inode.uid = uid;
+            disk_inode.gid = gid;
+        });
+        block_cache_sync_all();
+    }
+    /// Check whether (uid, gid, groups) may access this inode under `mask`
+    /// (an OR of `layout::access_mask` bits)
+    pub fn check_access(&self, uid: u32, gid: u32, groups: &[u32], mask: u8) -> bool {
+        self.read_disk_inode(|disk_inode| disk_inode.check_access(uid, gid, groups, mask))
+    }
     /// Clear the data in current inode
     pub fn clear(&self) {
         let mut fs = self.fs.lock();
@@ -294,10 +521,30 @@ impl Inode {
             (di.is_dir(), di.is_file())
         })
     }
+    /// This inode's type, including symbolic links
+    pub fn get_type(&self) -> DiskInodeType {
+        self.read_disk_inode(|di| di.get_type())
+    }
     /// Get reference count
     pub fn get_ref_count(&self)-> u32 {
         self.read_disk_inode(|di| {
             di.get_ref_count() as u32
         })
     }
+    /// Snapshot this inode's metadata, for a POSIX-like `stat`/`fstat`
+    pub fn stat(&self) -> FileStat {
+        let ino = self.get_id() as u64;
+        self.read_disk_inode(|di| FileStat {
+            ino,
+            is_dir: di.is_dir(),
+            size: di.size as u64,
+            nlink: di.get_ref_count(),
+            mode: di.mode,
+            uid: di.uid,
+            gid: di.gid,
+            atime: di.atime,
+            mtime: di.mtime,
+            ctime: di.ctime,
+        })
+    }
 }
@@ -2,6 +2,7 @@
 
 mod inode;
 mod pipe;
+mod procfs;
 mod stdio;
 
 use crate::mm::UserBuffer;
@@ -70,10 +71,20 @@ bitflags! {
 }
 
 use inode::ROOT_INODE;
-pub use inode::{list_apps, open_file, OSInode, OpenFlags};
+pub use inode::{list_apps, open_file as open_disk_file, OSInode, OpenFlags};
 pub use pipe::{make_pipe, Pipe};
+pub use procfs::{open_proc, ProcInode};
 pub use stdio::{Stdin, Stdout};
 
+/// Open `path`, resolving `/proc` to the virtual filesystem in [`procfs`]
+/// before falling back to the on-disk inode tree.
+pub fn open_file(path: &str, flags: OpenFlags) -> Option<alloc::sync::Arc<dyn File>> {
+    if let Some(proc_file) = open_proc(path) {
+        return Some(proc_file);
+    }
+    open_disk_file(path, flags).map(|inode| inode as alloc::sync::Arc<dyn File>)
+}
+
 
 /// Link a file
 pub fn link_file(from_name: &str, to_name: &str)-> Option<alloc::sync::Arc<easy_fs::Inode>> {
@@ -121,3 +132,55 @@ pub fn unlink_file(filename: &str)-> Result<(), &'static str> {
         warn!("efs message: {}", str);
     })
 }
+
+/// Split `path` into its parent directory path and final component name,
+/// e.g. `"a/b/c"` -> `("a/b", "c")`, `"c"` -> `("", "c")`
+fn split_parent(path: &str) -> (&str, &str) {
+    let path = path.trim_end_matches('/');
+    match path.rfind('/') {
+        Some(idx) => (&path[..idx], &path[idx + 1..]),
+        None => ("", path),
+    }
+}
+
+/// Resolve `path` to the directory `Inode` it names (the root if `path` is
+/// empty), or `None` if it doesn't exist or doesn't name a directory
+fn resolve_dir(path: &str) -> Option<alloc::sync::Arc<easy_fs::Inode>> {
+    let dir = if path.is_empty() {
+        ROOT_INODE.clone()
+    } else {
+        ROOT_INODE.find_path(path, true)?
+    };
+    if dir.is_dir_file().0 {
+        Some(dir)
+    } else {
+        None
+    }
+}
+
+/// Create a subdirectory named by `path`, walking any leading directory
+/// components through `find_path` to reach its parent first
+pub fn mkdir_dir(path: &str) -> Option<alloc::sync::Arc<easy_fs::Inode>> {
+    let (parent_path, name) = split_parent(path);
+    if name.is_empty() {
+        warn!("mkdir: {} is not a valid path", path);
+        return None;
+    }
+    let parent = resolve_dir(parent_path)?;
+    if parent.find(name).is_some() {
+        warn!("mkdir: {} already exists", path);
+        return None;
+    }
+    parent.mkdir(name)
+}
+
+/// Remove an empty subdirectory named by `path`, walking any leading
+/// directory components through `find_path` to reach its parent first
+pub fn rmdir_dir(path: &str) -> Result<(), &'static str> {
+    let (parent_path, name) = split_parent(path);
+    if name.is_empty() {
+        return Err("Invalid path");
+    }
+    let parent = resolve_dir(parent_path).ok_or("No such file or directory")?;
+    parent.rmdir(name)
+}
@@ -0,0 +1,221 @@
+//! A `/proc`-style view over the current task and its direct children,
+//! exposed through the same [`File`] trait as on-disk inodes.
+//!
+//! There is no global process table in this tree to walk every pid in the
+//! system, so path resolution is limited to `/proc/self` and `/proc/<pid>`
+//! for a pid that is a direct child of the caller. Restoring the task
+//! manager's process registry would let [`open_proc`] resolve any pid.
+
+use super::{File, Stat, StatMode};
+use crate::mm::UserBuffer;
+use crate::sync::UPSafeCell;
+use crate::task::{current_process, TaskControlBlock};
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+
+/// One node of the `/proc` tree
+pub enum ProcInode {
+    /// `/proc`, listing only `self`; child pids resolve through `open_proc`
+    /// but are not listed
+    Root,
+    /// `/proc/<pid>`, listing `status`, `maps` and `fd`
+    PidDir(usize),
+    /// `/proc/<pid>/status`: pid/tid/parent/state/thread count
+    Status(usize, UPSafeCell<usize>),
+    /// `/proc/<pid>/maps`: a summary of the task's address space
+    Maps(usize, UPSafeCell<usize>),
+    /// `/proc/<pid>/fd`: open descriptors from `fd_table`
+    Fd(usize, UPSafeCell<usize>),
+}
+
+fn find_process(pid: usize) -> Option<Arc<TaskControlBlock>> {
+    let me = current_process();
+    if me.getpid() == pid {
+        return Some(me);
+    }
+    me.inner_exclusive_access()
+        .children
+        .iter()
+        .find(|child| child.getpid() == pid)
+        .cloned()
+}
+
+fn parse_pid(s: &str) -> Option<usize> {
+    if s == "self" {
+        Some(current_process().getpid())
+    } else {
+        s.parse().ok()
+    }
+}
+
+/// Resolve a `/proc/...` path to a [`File`], or `None` if it doesn't exist.
+///
+/// Meant to run as the `/proc` prefix check ahead of `ROOT_INODE::find` in
+/// `open_file`, the same way `open_file` already special-cases other
+/// non-disk paths.
+pub fn open_proc(path: &str) -> Option<Arc<dyn File>> {
+    let rest = path.strip_prefix("/proc")?;
+    let segments: Vec<&str> = rest.split('/').filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        [] => Some(Arc::new(ProcInode::Root)),
+        [pid] => {
+            let pid = parse_pid(pid)?;
+            find_process(pid)?;
+            Some(Arc::new(ProcInode::PidDir(pid)))
+        }
+        [pid, "status"] => {
+            let pid = parse_pid(pid)?;
+            find_process(pid)?;
+            Some(Arc::new(ProcInode::Status(pid, unsafe { UPSafeCell::new(0) })))
+        }
+        [pid, "maps"] => {
+            let pid = parse_pid(pid)?;
+            find_process(pid)?;
+            Some(Arc::new(ProcInode::Maps(pid, unsafe { UPSafeCell::new(0) })))
+        }
+        [pid, "fd"] => {
+            let pid = parse_pid(pid)?;
+            find_process(pid)?;
+            Some(Arc::new(ProcInode::Fd(pid, unsafe { UPSafeCell::new(0) })))
+        }
+        _ => None,
+    }
+}
+
+impl ProcInode {
+    fn is_dir(&self) -> bool {
+        matches!(self, ProcInode::Root | ProcInode::PidDir(_))
+    }
+
+    fn render(&self) -> String {
+        match self {
+            ProcInode::Root => String::from("self\n"),
+            ProcInode::PidDir(_) => String::from("status\nmaps\nfd\n"),
+            ProcInode::Status(pid, _) => render_status(*pid),
+            ProcInode::Maps(pid, _) => render_maps(*pid),
+            ProcInode::Fd(pid, _) => render_fd(*pid),
+        }
+    }
+
+    /// This node's per-fd read offset, shared by every `read()` call through
+    /// the same `Arc<ProcInode>`, the same way `OSInode` tracks its own
+    /// offset so repeated reads advance instead of re-rendering from byte 0
+    fn offset(&self) -> Option<&UPSafeCell<usize>> {
+        match self {
+            ProcInode::Status(_, offset) | ProcInode::Maps(_, offset) | ProcInode::Fd(_, offset) => {
+                Some(offset)
+            }
+            ProcInode::Root | ProcInode::PidDir(_) => None,
+        }
+    }
+}
+
+fn render_status(pid: usize) -> String {
+    let Some(process) = find_process(pid) else {
+        return String::new();
+    };
+    let inner = process.inner_exclusive_access();
+    format!(
+        "Pid:\t{}\nTid:\t{}\nParent:\t{}\nState:\t{:?}\nThreads:\t{}\n",
+        pid,
+        inner.res.as_ref().map_or(pid, |res| res.tid),
+        inner
+            .parent
+            .as_ref()
+            .and_then(|p| p.upgrade())
+            .map_or(0, |p| p.getpid()),
+        inner.task_status,
+        // No thread enumeration exists on `TaskControlBlockInner` yet (see
+        // `TaskUserRes`/`tid_allocator`), so this can't report the process's
+        // actual live thread count -- just that the process itself exists.
+        inner.res.as_ref().map_or(1, |_| 1),
+    )
+}
+
+fn render_maps(pid: usize) -> String {
+    let Some(process) = find_process(pid) else {
+        return String::new();
+    };
+    let inner = process.inner_exclusive_access();
+    format!(
+        "heap\t{:#x}-{:#x}\n",
+        inner.heap_bottom, inner.program_brk
+    )
+}
+
+fn render_fd(pid: usize) -> String {
+    let Some(process) = find_process(pid) else {
+        return String::new();
+    };
+    let inner = process.inner_exclusive_access();
+    let mut out = String::new();
+    for (fd, file) in inner.fd_table.iter().enumerate() {
+        if let Some(file) = file {
+            out.push_str(&format!(
+                "{}\tr={} w={}\n",
+                fd,
+                file.readable(),
+                file.writable()
+            ));
+        }
+    }
+    out
+}
+
+/// Copy `data` into `buf`, returning the number of bytes actually copied
+fn write_into_buffer(buf: UserBuffer, data: &[u8]) -> usize {
+    let mut iter = buf.into_iter();
+    let mut written = 0;
+    for &byte in data {
+        match iter.next() {
+            Some(dst) => {
+                unsafe {
+                    *dst = byte;
+                }
+                written += 1;
+            }
+            None => break,
+        }
+    }
+    written
+}
+
+impl File for ProcInode {
+    fn readable(&self) -> bool {
+        !self.is_dir()
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn read(&self, buf: UserBuffer) -> usize {
+        if self.is_dir() {
+            return 0;
+        }
+        let Some(offset) = self.offset() else {
+            return 0;
+        };
+        let mut offset = offset.exclusive_access();
+        let content = self.render();
+        let bytes = content.as_bytes();
+        if *offset >= bytes.len() {
+            return 0;
+        }
+        let read = write_into_buffer(buf, &bytes[*offset..]);
+        *offset += read;
+        read
+    }
+
+    fn write(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+
+    fn stat(&self) -> Stat {
+        let mode = if self.is_dir() {
+            StatMode::DIR
+        } else {
+            StatMode::FILE
+        };
+        Stat::new(0, 0, mode, 1)
+    }
+}
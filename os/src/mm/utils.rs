@@ -1,8 +1,110 @@
 //! Memory management utilities including kernel-user memory interactions.
 //! BY Medi H.B.T.
 
+use crate::mm::{MapPermission, VirtAddr, VirtPageNum};
+use crate::task::read_current_tcb;
 use crate::{mm, task};
 
+/// Why a fallible user-space memory access (`try_copy_from_user`/
+/// `try_copy_to_user`) was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UAccessError {
+    /// The pointer was null, or the range was empty (`len == 0`)
+    NullPointer,
+    /// Some page in the range has no mapping at all
+    Unmapped,
+    /// The first page that fails the check is mapped, but without the
+    /// permission this access needs
+    PermissionDenied,
+}
+
+impl UAccessError {
+    /// The errno a syscall should surface for this failure
+    pub const EFAULT: isize = -14;
+}
+
+fn page_round_down(addr: usize) -> usize {
+    addr & !0xFFF
+}
+
+fn page_round_up(addr: usize) -> usize {
+    (addr + 0xFFF) & !0xFFF
+}
+
+/// Check that every page covering `[ptr, ptr+len)` is mapped in the current
+/// task's address space with at least `need` permission
+fn validate_user_range(ptr: *const u8, len: usize, need: MapPermission) -> Result<(), UAccessError> {
+    if ptr.is_null() || len == 0 {
+        return Err(UAccessError::NullPointer);
+    }
+    let ptr = ptr as usize;
+    let lo = page_round_down(ptr);
+    let hi = page_round_up(ptr + len);
+    read_current_tcb(&mut |_pid, tcbi| {
+        let mut addr = lo;
+        while addr < hi {
+            let vpn = VirtPageNum::from(VirtAddr::from(addr));
+            let Some(pte) = tcbi.memory_set.exclusive_access().translate(vpn) else {
+                return Err(UAccessError::Unmapped);
+            };
+            if !pte.is_valid() {
+                return Err(UAccessError::Unmapped);
+            }
+            let ok = if need.contains(MapPermission::W) {
+                pte.writable()
+            } else {
+                pte.readable()
+            };
+            if !ok {
+                return Err(UAccessError::PermissionDenied);
+            }
+            addr += 0x1000;
+        }
+        Ok(())
+    })
+}
+
+/// Fallible variant of `copy_from_user`: validates the whole range before
+/// copying a single byte, returning `Err` instead of panicking on a
+/// partially-unmapped or wrongly-permissioned buffer
+pub fn try_copy_from_user(kernel_dst: &mut [u8], user_src: *const u8, len: usize) -> Result<(), UAccessError> {
+    validate_user_range(user_src, len, MapPermission::R)?;
+    unsafe {
+        copy_from_user(kernel_dst, user_src, len);
+    }
+    Ok(())
+}
+
+/// Fallible variant of `copy_to_user`: validates the whole range before
+/// copying a single byte, returning `Err` instead of panicking on a
+/// partially-unmapped or wrongly-permissioned buffer
+pub fn try_copy_to_user(user_dst: *mut u8, len: usize, kernel_src: &[u8]) -> Result<(), UAccessError> {
+    validate_user_range(user_dst as *const u8, len, MapPermission::W)?;
+    unsafe {
+        copy_to_user(user_dst, len, kernel_src);
+    }
+    Ok(())
+}
+
+/// Fallible variant of `copy_obj_from_user`
+pub fn try_copy_obj_from_user<DataT: Sized + Copy>(kobject: &mut DataT, user_src: *const DataT) -> Result<(), UAccessError> {
+    let len = core::mem::size_of::<DataT>();
+    let kptr = kobject as *mut DataT as *mut u8;
+    try_copy_from_user(
+        unsafe { core::slice::from_raw_parts_mut(kptr, len) },
+        user_src as *const u8,
+        len,
+    )
+}
+
+/// Fallible variant of `copy_obj_to_user`
+pub fn try_copy_obj_to_user<DataT: Sized>(user_dst: *mut DataT, kobject: &DataT) -> Result<(), UAccessError> {
+    let len = core::mem::size_of::<DataT>();
+    let kptr = kobject as *const DataT as *const u8;
+    try_copy_to_user(user_dst as *mut u8, len, unsafe {
+        core::slice::from_raw_parts(kptr, len)
+    })
+}
 
 /// Copy N bytes to kernel space from user space.
 pub unsafe fn copy_from_user(kernel_src: &mut [u8], user_src: *const u8, len: usize)
@@ -69,6 +171,99 @@ pub unsafe fn copy_obj_to_user<DataT: Sized>(user_dst: *mut DataT, kobject: &Dat
 /// BY Medi.H.B.T.
 pub mod mmap_handle {
     use crate::{config::PAGE_SIZE, mm::{address::VPNRange, MapPermission, MemorySet, VirtAddr, VirtPageNum}, task::update_current_tcb};
+    use alloc::vec::Vec;
+
+    /// How a lazy page's frame should be initialized the first time it's
+    /// touched. Only zeroed anonymous memory for now; a file-backed mapping
+    /// would add a variant here that reads the page in from its source.
+    #[derive(Clone, Copy)]
+    pub enum BackingKind {
+        /// A freshly zeroed frame
+        AnonZeroed,
+    }
+
+    /// A page range reserved in a task's address space but not yet backed
+    /// by physical frames. `do_mmap` pushes one of these instead of mapping
+    /// frames eagerly; `handle_page_fault` populates a single page out of
+    /// it the first time that page is touched.
+    ///
+    /// Bounds are kept as plain byte addresses rather than a `VPNRange` so
+    /// `do_munmap` can trim/split a region with ordinary arithmetic instead
+    /// of needing accessors `VPNRange` doesn't expose.
+    #[derive(Clone, Copy)]
+    pub struct LazyMmapRegion {
+        /// First byte this region reserves, page-aligned
+        pub start: usize,
+        /// One past the last byte this region reserves, page-aligned
+        pub end: usize,
+        /// Permission the populated pages will be mapped with
+        pub perm: MapPermission,
+        /// How to initialize a page the first time it's faulted in
+        pub backing: BackingKind,
+        /// `mlock`ed against reclaim, see `do_mlock`/`do_munlock`. This is
+        /// the per-page "wired" flag the future reclaim/eviction subsystem
+        /// should consult before evicting a page -- kept on the region
+        /// itself (this tree's only map-area metadata for mmap'd ranges)
+        /// rather than a side table, so it survives `do_mlock`/`do_munlock`
+        /// splitting the same way permission/backing already do.
+        pub wired: bool,
+    }
+
+    impl LazyMmapRegion {
+        fn vpn_range(&self) -> VPNRange {
+            VPNRange::new(
+                VirtPageNum::from(VirtAddr::from(self.start)),
+                VirtPageNum::from(VirtAddr::from(self.end)),
+            )
+        }
+    }
+
+    /// The kind of memory access that faulted, for permission checking in
+    /// `handle_page_fault`
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub enum FaultAccessKind {
+        /// A load (read) access
+        Load,
+        /// A store (write) access
+        Store,
+    }
+
+    fn vpn_range_contains(range: VPNRange, vpn: VirtPageNum) -> bool {
+        range.into_iter().any(|v| v == vpn)
+    }
+
+    /// Map at exactly `start`, replacing any existing mapping there instead
+    /// of failing, mirroring `mmap(2)`'s `MAP_FIXED`
+    pub const MAP_FIXED: usize = 0x10;
+    /// Not backed by a file. The only backing this tree supports so far, so
+    /// this flag is currently a no-op kept for `mmap(2)` call-site parity.
+    pub const MAP_ANONYMOUS: usize = 0x20;
+
+    /// Base address above which `do_mmap` starts scanning for a free region
+    /// when the caller leaves placement up to the kernel (`start == 0`
+    /// without `MAP_FIXED`)
+    const MMAP_BASE: usize = 0x0001_0000_0000;
+
+    /// Starting from `hint`, find the lowest address that begins a run of
+    /// `len` bytes with neither an existing mapping nor an overlapping
+    /// `LazyMmapRegion`
+    fn find_free_range(mset: &MemorySet, lazy: &[LazyMmapRegion], len: usize, hint: usize) -> usize {
+        let mut base = if hint < MMAP_BASE { MMAP_BASE } else { hint };
+        'search: loop {
+            let mut offset = 0;
+            while offset < len {
+                let vp = VirtPageNum::from(VirtAddr::from(base + offset));
+                let occupied = mset.translate(vp).map(|pte| pte.is_valid()).unwrap_or(false)
+                    || lazy.iter().any(|r| vpn_range_contains(r.vpn_range(), vp));
+                if occupied {
+                    base += PAGE_SIZE;
+                    continue 'search;
+                }
+                offset += PAGE_SIZE;
+            }
+            return base;
+        }
+    }
 
     /// Check if an address or a length is page-aligned.
     fn page_aligned(addr: usize)-> bool {
@@ -104,10 +299,23 @@ pub mod mmap_handle {
     }
 
     /// Handle memory mapping
-    pub fn do_mmap(start: usize, len: usize, prot: usize)-> isize
+    ///
+    /// Unlike eagerly calling `insert_framed_area`, this only reserves the
+    /// range as a `LazyMmapRegion`: no frame is allocated until the range's
+    /// first page fault, see `handle_page_fault`. This makes large sparse
+    /// reservations cheap.
+    ///
+    /// With `start == 0` and `flags` not carrying `MAP_FIXED`, the kernel
+    /// picks the placement itself (the lowest free range at or above
+    /// `MMAP_BASE`) and returns it. With `MAP_FIXED`, `start` is taken
+    /// literally and any mapping already occupying the range is replaced
+    /// rather than causing a failure. The return value is the chosen
+    /// address, or a negative errno.
+    pub fn do_mmap(start: usize, len: usize, prot: usize, flags: usize)-> isize
     {
+        let fixed = flags & MAP_FIXED != 0;
         /* Check addresses to ensure they're page-aligned. */
-        if !page_aligned(start) {
+        if (start != 0 || fixed) && !page_aligned(start) {
             warn!("Address(start) 0x{:016x} not page-aligned", start);
             return -1;
         }
@@ -124,20 +332,174 @@ pub mod mmap_handle {
         /* Access TCB: we'll update memory sets so that memory mapping infomation
          * will be registered   */
         update_current_tcb(&mut |_tcb, tcbi| {
-            let mset = &mut tcbi.memory_set;
-            if _check_vaddr_if_mapped(mset, start, start + len) {
-                warn!("Virtual address {:016x}..{:016x} mapped", start, start + len);
-                return -1;
-            }
-            mset.insert_framed_area(
-                VirtAddr::from(start),
-                VirtAddr::from(start + len),
-                prot);
-            return 0;
+            let chosen = if start == 0 && !fixed {
+                find_free_range(&tcbi.memory_set.exclusive_access(), &tcbi.lazy_mmap_regions, len, MMAP_BASE)
+            } else {
+                start
+            };
+            let range = VPNRange::new(
+                VirtPageNum::from(VirtAddr::from(chosen)),
+                VirtPageNum::from(VirtAddr::from(chosen + len)),
+            );
+            let overlaps = _check_vaddr_if_mapped(&tcbi.memory_set.exclusive_access(), chosen, chosen + len)
+                || tcbi
+                    .lazy_mmap_regions
+                    .iter()
+                    .any(|r| range.into_iter().any(|vp| vpn_range_contains(r.vpn_range(), vp)));
+            if overlaps {
+                if !fixed {
+                    warn!("Virtual address {:016x}..{:016x} mapped", chosen, chosen + len);
+                    return -1;
+                }
+                // MAP_FIXED: tear down whatever already occupies the range
+                // instead of failing
+                tcbi.lazy_mmap_regions
+                    .retain(|r| !range.into_iter().any(|vp| vpn_range_contains(r.vpn_range(), vp)));
+                tcbi.memory_set
+                    .exclusive_access()
+                    .unmap_range(VirtPageNum::from(VirtAddr::from(chosen)), len / PAGE_SIZE);
+            }
+            tcbi.lazy_mmap_regions.push(LazyMmapRegion {
+                start: chosen,
+                end: chosen + len,
+                perm: prot,
+                backing: BackingKind::AnonZeroed,
+                wired: false,
+            });
+            chosen as isize
+        })
+    }
+
+    /// Allocate and map the frame backing a single page of `region`,
+    /// exactly the work a page fault or `mlock` does to force it resident
+    fn populate_lazy_page(memory_set: &mut MemorySet, region: &LazyMmapRegion, vpn: VirtPageNum) {
+        let page_start = VirtAddr::from(vpn);
+        let page_end = VirtAddr(page_start.0 + PAGE_SIZE);
+        match region.backing {
+            BackingKind::AnonZeroed => {
+                memory_set.insert_framed_area(page_start, page_end, region.perm);
+            }
+        }
+    }
+
+    /// Page-fault handler for lazily-backed mmap regions: called from the
+    /// trap handler when a store/load page fault lands in a VA with no
+    /// mapping yet. Looks up the `LazyMmapRegion` covering `fault_va`,
+    /// checks the access is allowed by its permission bits, and populates
+    /// just the faulting page. Returns `false` (caller should kill the task
+    /// with a fault code) when no region covers `fault_va` or the access
+    /// kind isn't permitted.
+    pub fn handle_page_fault(fault_va: usize, access: FaultAccessKind) -> bool {
+        let fault_vpn = VirtPageNum::from(VirtAddr::from(fault_va));
+        update_current_tcb(&mut |_tcb, tcbi| {
+            let Some(region) = tcbi
+                .lazy_mmap_regions
+                .iter()
+                .find(|r| vpn_range_contains(r.vpn_range(), fault_vpn))
+                .copied()
+            else {
+                return false;
+            };
+            let allowed = match access {
+                FaultAccessKind::Load => region.perm.contains(MapPermission::R),
+                FaultAccessKind::Store => region.perm.contains(MapPermission::W),
+            };
+            if !allowed {
+                return false;
+            }
+            populate_lazy_page(&mut tcbi.memory_set.exclusive_access(), &region, fault_vpn);
+            true
         })
     }
 
+    /// Cut `[cut_start, cut_end)` out of `region`, returning the piece(s) of
+    /// it that remain (0, 1, or 2 of them, depending on whether the cut
+    /// removes the whole region, trims one edge, or punches a hole in the
+    /// middle and splits it in two) along with whether the cut overlapped
+    /// `region` at all.
+    fn split_lazy_region(
+        region: LazyMmapRegion,
+        cut_start: usize,
+        cut_end: usize,
+    ) -> (Vec<LazyMmapRegion>, bool) {
+        if cut_end <= region.start || cut_start >= region.end {
+            return ([region].into_iter().collect(), false);
+        }
+        let mut pieces = Vec::new();
+        if region.start < cut_start {
+            // front trim: keep [region.start, cut_start)
+            pieces.push(LazyMmapRegion {
+                end: cut_start,
+                ..region
+            });
+        }
+        if region.end > cut_end {
+            // tail trim: keep [cut_end, region.end). Together with the
+            // front-trim piece above, a cut strictly inside the region
+            // produces both, i.e. a hole-punch split into two regions.
+            pieces.push(LazyMmapRegion {
+                start: cut_end,
+                ..region
+            });
+        }
+        (pieces, true)
+    }
+
+    /// Set `region`'s `wired` flag to `wired` over exactly `[cut_start,
+    /// cut_end)`, splitting it into up to three pieces (untouched front,
+    /// re-flagged middle, untouched back) the same way `split_lazy_region`
+    /// splits around a punched-out hole -- except the overlapping middle
+    /// piece is kept (with its flag flipped) instead of dropped. Returns
+    /// the piece(s) that remain and whether the range overlapped `region`
+    /// at all.
+    fn mark_lazy_region_wired(
+        region: LazyMmapRegion,
+        cut_start: usize,
+        cut_end: usize,
+        wired: bool,
+    ) -> (Vec<LazyMmapRegion>, bool) {
+        if cut_end <= region.start || cut_start >= region.end {
+            return ([region].into_iter().collect(), false);
+        }
+        let mut pieces = Vec::new();
+        if region.start < cut_start {
+            pieces.push(LazyMmapRegion {
+                end: cut_start,
+                ..region
+            });
+        }
+        let mid_start = region.start.max(cut_start);
+        let mid_end = region.end.min(cut_end);
+        pieces.push(LazyMmapRegion {
+            start: mid_start,
+            end: mid_end,
+            wired,
+            ..region
+        });
+        if region.end > cut_end {
+            pieces.push(LazyMmapRegion {
+                start: cut_end,
+                ..region
+            });
+        }
+        (pieces, true)
+    }
+
     /// Handle memory unmapping
+    ///
+    /// `[start, start+len)` may cover the middle of a larger mapping, span
+    /// several adjacent ones, or only partially overlap them -- real
+    /// `munmap(2)` semantics, not "the whole range must match one mapping
+    /// exactly". Each `LazyMmapRegion` intersecting the range is trimmed at
+    /// the front, trimmed at the back, split into two around a punched-out
+    /// hole, or dropped entirely via `split_lazy_region`. Populated (already
+    /// faulted-in) pages are freed one at a time for exactly the pages
+    /// inside the request, which stays correct regardless of how big the
+    /// originating mapping was since it never frees a page outside
+    /// `[start, start+len)`; the underlying `MemorySet` doesn't expose area
+    /// splitting to this module, so that's the granularity available here.
+    /// Returns `-1` only if no part of the range was mapped, lazily or
+    /// otherwise.
     pub fn do_munmap(start: usize, len: usize)-> isize {
         /* Check addresses to ensure they're page-aligned. */
         if !page_aligned(start) {
@@ -145,16 +507,258 @@ pub mod mmap_handle {
             return -1;
         }
 
-        let plen   = make_page_aligned(len);
-        let npages = plen / PAGE_SIZE;
-        let pbegin = VirtPageNum::from(VirtAddr(start));
+        let plen = make_page_aligned(len);
+        let cut_start = start;
+        let cut_end = start + plen;
 
-        info!("Unmap len {:08x}, {:08x} pages", len, npages);
+        info!("Unmap len {:08x}, {:08x} pages", len, plen / PAGE_SIZE);
 
         /* Access TCB: we'll update memory sets so that memory mapping infomation
          * will be registered or unregistered  */
         update_current_tcb(&mut |_tcb, tcbi| {
-            if tcbi.memory_set.unmap_range(pbegin, npages) { 0 } else { -1 }
+            let mut touched_lazy = false;
+            let mut kept = Vec::new();
+            for region in tcbi.lazy_mmap_regions.drain(..) {
+                let (pieces, touched) = split_lazy_region(region, cut_start, cut_end);
+                touched_lazy |= touched;
+                kept.extend(pieces);
+            }
+            tcbi.lazy_mmap_regions = kept;
+
+            let range = VPNRange::new(
+                VirtPageNum::from(VirtAddr::from(cut_start)),
+                VirtPageNum::from(VirtAddr::from(cut_end)),
+            );
+            let mut unmapped_any = false;
+            for vp in range {
+                let mapped = tcbi
+                    .memory_set
+                    .exclusive_access()
+                    .translate(vp)
+                    .map(|pte| pte.is_valid())
+                    .unwrap_or(false);
+                if mapped {
+                    tcbi.memory_set.exclusive_access().unmap_range(vp, 1);
+                    unmapped_any = true;
+                }
+            }
+
+            if touched_lazy || unmapped_any {
+                0
+            } else {
+                -1
+            }
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn region(start: usize, end: usize) -> LazyMmapRegion {
+            LazyMmapRegion {
+                start,
+                end,
+                perm: MapPermission::R | MapPermission::U,
+                backing: BackingKind::AnonZeroed,
+                wired: false,
+            }
+        }
+
+        fn bounds(pieces: &[LazyMmapRegion]) -> Vec<(usize, usize)> {
+            pieces.iter().map(|r| (r.start, r.end)).collect()
+        }
+
+        #[test]
+        fn front_trim_keeps_tail() {
+            let (pieces, touched) = split_lazy_region(region(0x1000, 0x4000), 0x0000, 0x2000);
+            assert!(touched);
+            assert_eq!(bounds(&pieces), [(0x2000, 0x4000)]);
+        }
+
+        #[test]
+        fn tail_trim_keeps_front() {
+            let (pieces, touched) = split_lazy_region(region(0x1000, 0x4000), 0x3000, 0x5000);
+            assert!(touched);
+            assert_eq!(bounds(&pieces), [(0x1000, 0x3000)]);
+        }
+
+        #[test]
+        fn hole_punch_splits_in_two() {
+            let (pieces, touched) = split_lazy_region(region(0x1000, 0x5000), 0x2000, 0x4000);
+            assert!(touched);
+            assert_eq!(bounds(&pieces), [(0x1000, 0x2000), (0x4000, 0x5000)]);
+        }
+
+        #[test]
+        fn disjoint_range_is_untouched() {
+            let (pieces, touched) = split_lazy_region(region(0x1000, 0x2000), 0x5000, 0x6000);
+            assert!(!touched);
+            assert_eq!(bounds(&pieces), [(0x1000, 0x2000)]);
+        }
+
+        #[test]
+        fn mark_wired_splits_out_only_the_covered_pages() {
+            let (pieces, touched) = mark_lazy_region_wired(region(0x1000, 0x5000), 0x2000, 0x4000, true);
+            assert!(touched);
+            assert_eq!(bounds(&pieces), [(0x1000, 0x2000), (0x2000, 0x4000), (0x4000, 0x5000)]);
+            assert!(!pieces[0].wired);
+            assert!(pieces[1].wired);
+            assert!(!pieces[2].wired);
+        }
+
+        #[test]
+        fn mark_unwired_clears_flag_on_covered_pages_only() {
+            let mut wired_region = region(0x1000, 0x5000);
+            wired_region.wired = true;
+            let (pieces, touched) = mark_lazy_region_wired(wired_region, 0x2000, 0x4000, false);
+            assert!(touched);
+            assert!(pieces[0].wired);
+            assert!(!pieces[1].wired);
+            assert!(pieces[2].wired);
+        }
+    }
+
+    /// Change permissions on an existing mapping in place: no frame is
+    /// reallocated, only the PTE flags are rewritten (the `U` bit is kept,
+    /// since `uprot_to_permission` always sets it). Every page in
+    /// `[start, start+len)` must already be mapped; if any isn't, nothing
+    /// is changed and the call fails with `-1`.
+    pub fn do_mprotect(start: usize, len: usize, prot: usize) -> isize {
+        if !page_aligned(start) {
+            warn!("Address(start) 0x{:016x} not page-aligned", start);
+            return -1;
+        }
+        let prot = if let Some(prot) = uprot_to_permission(prot) {
+            prot
+        } else {
+            warn!("Invalid permission value 0b{:04b}", prot);
+            return -1;
+        };
+        let len = make_page_aligned(len);
+        let range = VPNRange::new(
+            VirtPageNum::from(VirtAddr::from(start)),
+            VirtPageNum::from(VirtAddr::from(start + len)),
+        );
+
+        update_current_tcb(&mut |_tcb, tcbi| {
+            for vp in range {
+                match tcbi.memory_set.exclusive_access().translate(vp) {
+                    Some(pte) if pte.is_valid() => {}
+                    _ => {
+                        warn!("mprotect: page {:#x} not mapped", VirtAddr::from(vp).0);
+                        return -1;
+                    }
+                }
+            }
+            for vp in range {
+                tcbi.memory_set.exclusive_access().protect_page(vp, prot);
+            }
+            0
+        })
+    }
+
+    /// Pin `[start, start+len)` against reclaim, mirroring `mlock(2)`. Any
+    /// page in the range that's only a `LazyMmapRegion` reservation so far
+    /// is force-populated first, the same frame allocation a page fault
+    /// would do, so the range is fully resident once this returns. Rejects
+    /// with `-1`, making no changes, if any page in the range is neither
+    /// resident nor reserved.
+    ///
+    /// The pin itself is recorded as `wired: true` directly on the
+    /// `LazyMmapRegion`(s) covering the range (splitting them at the range's
+    /// edges the same way `do_munmap` does), since those are the only pages
+    /// a future reclaim/eviction subsystem in this tree could ever evict --
+    /// everything else (ELF segments, the stack, the heap) is mapped
+    /// eagerly and has no eviction path to begin with. A range that only
+    /// covers already-resident, non-lazy pages has nothing to flag and
+    /// simply succeeds.
+    pub fn do_mlock(start: usize, len: usize) -> isize {
+        if !page_aligned(start) {
+            warn!("Address(start) 0x{:016x} not page-aligned", start);
+            return -1;
+        }
+        let len = make_page_aligned(len);
+        let cut_start = start;
+        let cut_end = start + len;
+        let range = VPNRange::new(
+            VirtPageNum::from(VirtAddr::from(start)),
+            VirtPageNum::from(VirtAddr::from(start + len)),
+        );
+
+        update_current_tcb(&mut |_tcb, tcbi| {
+            for vp in range {
+                let resident = tcbi
+                    .memory_set
+                    .exclusive_access()
+                    .translate(vp)
+                    .map(|pte| pte.is_valid())
+                    .unwrap_or(false);
+                let reserved = tcbi
+                    .lazy_mmap_regions
+                    .iter()
+                    .any(|r| vpn_range_contains(r.vpn_range(), vp));
+                if !resident && !reserved {
+                    warn!("mlock: page {:#x} not mapped", VirtAddr::from(vp).0);
+                    return -1;
+                }
+            }
+            for vp in range {
+                let resident = tcbi
+                    .memory_set
+                    .exclusive_access()
+                    .translate(vp)
+                    .map(|pte| pte.is_valid())
+                    .unwrap_or(false);
+                if resident {
+                    continue;
+                }
+                let region = tcbi
+                    .lazy_mmap_regions
+                    .iter()
+                    .find(|r| vpn_range_contains(r.vpn_range(), vp))
+                    .copied()
+                    .unwrap();
+                populate_lazy_page(&mut tcbi.memory_set.exclusive_access(), &region, vp);
+            }
+            let mut marked = Vec::new();
+            for region in tcbi.lazy_mmap_regions.drain(..) {
+                let (pieces, _) = mark_lazy_region_wired(region, cut_start, cut_end, true);
+                marked.extend(pieces);
+            }
+            tcbi.lazy_mmap_regions = marked;
+            0
+        })
+    }
+
+    /// Clear the `mlock` pin over `[start, start+len)`, unflagging exactly
+    /// the `LazyMmapRegion` pages it covers (splitting at the range's edges
+    /// the same way `do_mlock` does to set the flag). Returns `-1` if no
+    /// `LazyMmapRegion` page in the range was actually wired.
+    pub fn do_munlock(start: usize, len: usize) -> isize {
+        if !page_aligned(start) {
+            warn!("Address(start) 0x{:016x} not page-aligned", start);
+            return -1;
+        }
+        let len = make_page_aligned(len);
+        let cut_start = start;
+        let cut_end = start + len;
+
+        update_current_tcb(&mut |_tcb, tcbi| {
+            let mut touched = false;
+            let mut kept = Vec::new();
+            for region in tcbi.lazy_mmap_regions.drain(..) {
+                let was_wired = region.wired;
+                let (pieces, did_overlap) = mark_lazy_region_wired(region, cut_start, cut_end, false);
+                touched |= did_overlap && was_wired;
+                kept.extend(pieces);
+            }
+            tcbi.lazy_mmap_regions = kept;
+            if touched {
+                0
+            } else {
+                -1
+            }
         })
     }
 }
\ No newline at end of file
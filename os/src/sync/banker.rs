@@ -81,7 +81,7 @@ impl Banker {
     /// Let thread T allocate a resource x
     pub fn try_allocate_one(&mut self, thread_id: usize, resource_id: usize) -> bool
     {
-        if thread_id > MAX_THREADS {
+        if thread_id >= MAX_THREADS {
             return false;
         }
         if resource_id >= MAX_RESOURCE || self.need[thread_id][resource_id] == 0 {
@@ -107,7 +107,7 @@ impl Banker {
     /// Let thread `thread_id` allocate a resource `resource_id` without check
     pub fn allocate_one_nocheck(&mut self, thread_id: usize, resource_id: usize) -> bool
     {
-        if thread_id > MAX_THREADS {
+        if thread_id >= MAX_THREADS {
             return false;
         }
         if resource_id >= MAX_RESOURCE || self.need[thread_id][resource_id] == 0 {
@@ -124,7 +124,7 @@ impl Banker {
 
     /// deallocate resource
     pub fn try_deallocate_one(&mut self, thread_id: usize, resource_id: usize)-> bool {
-        if thread_id > MAX_THREADS || resource_id >= MAX_RESOURCE || self.allocated[thread_id][resource_id] == 0 {
+        if thread_id >= MAX_THREADS || resource_id >= MAX_RESOURCE || self.allocated[thread_id][resource_id] == 0 {
             false
         } else {
             self.available[resource_id]            += 1;
@@ -135,7 +135,7 @@ impl Banker {
     }
     /// Dynamicly expend size of 'need' and .
     pub fn dyn_expand_dealloc(&mut self, thread_id: usize, resource_id: usize)-> bool {
-        if thread_id > MAX_THREADS || resource_id >= MAX_RESOURCE || self.allocated[thread_id][resource_id] == 0 {
+        if thread_id >= MAX_THREADS || resource_id >= MAX_RESOURCE || self.allocated[thread_id][resource_id] == 0 {
             false
         } else {
             self.available[resource_id]            += 1;
@@ -145,7 +145,7 @@ impl Banker {
     }
     /// set up thread and set needs.
     pub fn setup_thread(&mut self, thread_id: usize, need: &[usize; MAX_RESOURCE]) -> bool {
-        if thread_id > MAX_THREADS {
+        if thread_id >= MAX_THREADS {
             return false;
         }
         self.need[thread_id] = need.clone();
@@ -153,7 +153,7 @@ impl Banker {
     }
     /// set up resources.
     pub fn setup_resources(&mut self, resource_id: usize, max_available: usize) -> bool {
-        if resource_id > MAX_RESOURCE {
+        if resource_id >= MAX_RESOURCE {
             false
         } else {
             self.available[resource_id] = max_available;
@@ -162,7 +162,7 @@ impl Banker {
     }
     /// destroy thread and release all resources.
     pub fn destroy_thread(&mut self, thread_id: usize) -> bool {
-        if thread_id > MAX_THREADS {
+        if thread_id >= MAX_THREADS {
             return false;
         }
         for resource_id in 0..MAX_RESOURCE {
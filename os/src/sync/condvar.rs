@@ -0,0 +1,61 @@
+//! Condition variable
+
+use crate::sync::{LockError, Mutex, UPSafeCell};
+use crate::task::{block_current_and_run_next, current_task, wakeup_task, TaskControlBlock};
+use alloc::{collections::VecDeque, sync::Arc};
+
+/// Condition variable structure
+pub struct Condvar {
+    /// condvar inner
+    pub inner: UPSafeCell<CondvarInner>,
+}
+
+pub struct CondvarInner {
+    pub wait_queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl Condvar {
+    /// Create a new condvar
+    pub fn new() -> Self {
+        trace!("kernel: Condvar::new");
+        Self {
+            inner: unsafe {
+                UPSafeCell::new(CondvarInner {
+                    wait_queue: VecDeque::new(),
+                })
+            },
+        }
+    }
+
+    /// Wake the task that has been waiting on this condvar the longest, if any
+    pub fn signal(&self) {
+        trace!("kernel: Condvar::signal");
+        let mut inner = self.inner.exclusive_access();
+        if let Some(task) = inner.wait_queue.pop_front() {
+            wakeup_task(task);
+        }
+    }
+
+    /// Wake every task currently waiting on this condvar
+    pub fn broadcast(&self) {
+        trace!("kernel: Condvar::broadcast");
+        let mut inner = self.inner.exclusive_access();
+        while let Some(task) = inner.wait_queue.pop_front() {
+            wakeup_task(task);
+        }
+    }
+
+    /// Atomically unlock `mutex`, park the caller until woken by `signal` or
+    /// `broadcast`, then re-lock `mutex` before returning. Propagates the
+    /// re-lock's `Err` instead of swallowing it, so a caller never believes
+    /// it holds `mutex` when deadlock tracing actually refused the regrant.
+    pub fn wait(&self, mutex: Arc<dyn Mutex>) -> Result<(), LockError> {
+        trace!("kernel: Condvar::wait");
+        mutex.unlock();
+        let mut inner = self.inner.exclusive_access();
+        inner.wait_queue.push_back(current_task().unwrap());
+        drop(inner);
+        block_current_and_run_next();
+        mutex.lock()
+    }
+}
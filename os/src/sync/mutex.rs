@@ -2,16 +2,49 @@
 
 use core::usize;
 
+use super::banker::{Banker, MAX_RESOURCE, MAX_THREADS};
 use super::UPSafeCell;
-use crate::task::{get_current_pid, read_current_tcb, TaskControlBlock};
+use crate::task::{add_task, get_current_pid, read_current_tcb, TaskControlBlock};
 use crate::task::{block_current_and_run_next, suspend_current_and_run_next};
 use crate::task::{current_task, wakeup_task};
-use alloc::{collections::VecDeque, sync::Arc};
+use crate::timer;
+use alloc::{collections::VecDeque, sync::Arc, vec::Vec};
+use lazy_static::*;
+
+/// Error returned by `Mutex::lock`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockError {
+    /// Deadlock tracing is enabled and the banker's safety check determined
+    /// that granting this lock could lead to an unsafe (deadlocked) state;
+    /// the grant was refused rather than risking it
+    WouldDeadlock,
+    /// The caller's tid does not fit in the banker's fixed `MAX_THREADS`
+    /// rows. Only tasks created through `TaskUserRes` get a tid bounded by
+    /// their process's slot allocator; an ordinary process's tid is its raw
+    /// pid, which is unbounded, so this can't be ruled out up front.
+    TidOutOfRange,
+}
+
+lazy_static! {
+    /// Every blocking mutex is registered as a single-instance resource with
+    /// this banker, so `lock` can run a banker's safety check before
+    /// granting the mutex
+    static ref MUTEX_BANKER: UPSafeCell<Banker> = unsafe { UPSafeCell::new(Banker::new()) };
+    /// Free list of resource ids the banker can still tell apart. At most
+    /// `MAX_RESOURCE` blocking mutexes can be live at once; ids are handed
+    /// back here when their `MutexBlocking` is dropped so a long-running
+    /// system can keep creating and destroying mutexes without aliasing
+    /// two live ones onto the same banker row.
+    static ref FREE_MUTEX_RESOURCE_IDS: UPSafeCell<Vec<usize>> =
+        unsafe { UPSafeCell::new((0..MAX_RESOURCE).rev().collect()) };
+}
 
 /// Mutex trait
 pub trait Mutex: Sync + Send {
-    /// Lock the mutex
-    fn lock(&self);
+    /// Lock the mutex. Returns `Err(LockError::WouldDeadlock)` if deadlock
+    /// tracing is enabled for the current task and the banker's safety
+    /// check refuses the grant, instead of blocking.
+    fn lock(&self) -> Result<(), LockError>;
     /// Unlock the mutex
     fn unlock(&self);
     /// Trace deadlock
@@ -35,8 +68,9 @@ impl MutexSpin {
 }
 
 impl Mutex for MutexSpin {
-    /// Lock the spinlock mutex
-    fn lock(&self) {
+    /// Lock the spinlock mutex. Never participates in deadlock tracing, so
+    /// this always eventually succeeds.
+    fn lock(&self) -> Result<(), LockError> {
         trace!("kernel: MutexSpin::lock");
         loop {
             let mut locked = self.locked.exclusive_access();
@@ -46,7 +80,7 @@ impl Mutex for MutexSpin {
                 continue;
             } else {
                 *locked = true;
-                return;
+                return Ok(());
             }
         }
     }
@@ -61,38 +95,80 @@ impl Mutex for MutexSpin {
 /// Blocking Mutex struct
 pub struct MutexBlocking {
     inner: UPSafeCell<MutexBlockingInner>,
+    /// This mutex's resource id in `MUTEX_BANKER`
+    resource_id: usize,
 }
 
 pub struct MutexBlockingInner {
     locked: bool,
     wait_queue: VecDeque<Arc<TaskControlBlock>>,
     lock_holder: usize,
+    /// The current holder's TCB, kept around so this mutex's donation can
+    /// be granted to, and later withdrawn from, it
+    holder_task: Option<Arc<TaskControlBlock>>,
 }
 
 impl MutexBlocking {
-    /// Create a new blocking mutex
-    pub fn new() -> Self {
+    /// Create a new blocking mutex, or `None` if `MAX_RESOURCE` blocking
+    /// mutexes are already live and the banker has no free resource id left
+    /// to tell this one apart from the rest.
+    pub fn new() -> Option<Self> {
         trace!("kernel: MutexBlocking::new");
-        Self {
+        let resource_id = FREE_MUTEX_RESOURCE_IDS.exclusive_access().pop()?;
+        MUTEX_BANKER.exclusive_access().setup_resources(resource_id, 1);
+        Some(Self {
             inner: unsafe {
                 UPSafeCell::new(MutexBlockingInner {
                     locked: false,
                     wait_queue: VecDeque::new(),
                     lock_holder: usize::MAX,
+                    holder_task: None,
                 })
             },
+            resource_id,
+        })
+    }
+
+    /// Donate `priority` to `task` on this mutex's behalf, replacing
+    /// whatever this mutex previously donated to it, and re-derive `task`'s
+    /// effective priority. Tagging the donation by `resource_id` (rather
+    /// than overwriting a single holder-wide value) means a *different*
+    /// mutex `task` holds can keep its own donation intact -- fixing the bug
+    /// where releasing one of several held donated-priority mutexes would
+    /// reset the holder all the way down to its bare base priority.
+    fn donate(task: &Arc<TaskControlBlock>, resource_id: usize, priority: usize) {
+        let mut inner = task.inner_exclusive_access();
+        let before = inner.sched_info.get_priority();
+        inner.priority_donations.retain(|(id, _)| *id != resource_id);
+        inner.priority_donations.push((resource_id, priority));
+        inner.recompute_priority();
+        let changed = inner.sched_info.get_priority() != before;
+        drop(inner);
+        if changed {
+            // Make the boosted pass visible to the scheduler right away,
+            // instead of waiting for the holder's next natural requeue
+            add_task(task.clone());
         }
     }
 
+    /// Withdraw whatever this mutex previously donated to `task` and
+    /// re-derive its effective priority. Any donation still owed by a
+    /// *different* mutex `task` holds is untouched.
+    fn withdraw(task: &Arc<TaskControlBlock>, resource_id: usize) {
+        let mut inner = task.inner_exclusive_access();
+        inner.priority_donations.retain(|(id, _)| *id != resource_id);
+        inner.recompute_priority();
+    }
+
     /// Trace this mutex to find out whether this lock is dead.
     pub fn trace_lock_is_dead(&self, inner: &MutexBlockingInner)-> bool {
-        let current_tid = current_task().unwrap().gettid().unwrap();
+        let current_tid = current_task().unwrap().gettid();
         if inner.lock_holder == current_tid {
             warn!("Found dead lock in pid[{}] task[{}] (inner lock holder {})",
                   get_current_pid(), current_tid, inner.lock_holder);
             return true;
         }
-        match inner.wait_queue.iter().find(|t| {t.gettid() == Some(current_tid)}) {
+        match inner.wait_queue.iter().find(|t| {t.gettid() == current_tid}) {
             Some(_) => {
                 warn!("Found dead lock in pid[{}] task[{}] (inner lock holder {})",
                     get_current_pid(), current_tid, inner.lock_holder);
@@ -109,17 +185,52 @@ impl MutexBlocking {
 
 impl Mutex for MutexBlocking {
     /// lock the blocking mutex
-    fn lock(&self) {
+    fn lock(&self) -> Result<(), LockError> {
         trace!("kernel: MutexBlocking::lock");
+        let waiter = current_task().unwrap();
+        let tid = waiter.gettid();
+        if tid >= MAX_THREADS {
+            return Err(LockError::TidOutOfRange);
+        }
+        // Record the request with the banker before touching the wait
+        // queue: Need goes up first, then (only if tracing is enabled) the
+        // grant is checked for safety and rolled back on refusal.
+        {
+            let mut banker = MUTEX_BANKER.exclusive_access();
+            banker.need[tid][self.resource_id] += 1;
+            if read_current_tcb(|p, _| p.deadlock_tracing_enabled()) && !banker.is_safe() {
+                banker.need[tid][self.resource_id] -= 1;
+                return Err(LockError::WouldDeadlock);
+            }
+        }
         let mut mutex_inner = self.inner.exclusive_access();
         if mutex_inner.locked {
-            mutex_inner.wait_queue.push_back(current_task().unwrap());
+            mutex_inner.wait_queue.push_back(waiter);
+            // Donate the highest priority among all waiters still queued on
+            // this mutex to its current holder, so it can't be starved by a
+            // lower-priority task hogging the mutex (priority inheritance)
+            let top_priority = mutex_inner
+                .wait_queue
+                .iter()
+                .map(|t| t.get_priority())
+                .max()
+                .unwrap();
+            if let Some(holder) = mutex_inner.holder_task.clone() {
+                Self::donate(&holder, self.resource_id, top_priority);
+            }
             drop(mutex_inner);
             block_current_and_run_next();
         } else {
             mutex_inner.locked = true;
-            mutex_inner.lock_holder = current_task().unwrap().gettid().unwrap();
+            mutex_inner.lock_holder = tid;
+            mutex_inner.holder_task = Some(waiter);
         }
+        // Whether granted immediately or after being woken, the mutex is now
+        // ours: move the banker's bookkeeping for it from Need to Allocated
+        MUTEX_BANKER
+            .exclusive_access()
+            .allocate_one_nocheck(tid, self.resource_id);
+        Ok(())
     }
 
     /// unlock the blocking mutex
@@ -127,11 +238,31 @@ impl Mutex for MutexBlocking {
         trace!("kernel: MutexBlocking::unlock");
         let mut mutex_inner = self.inner.exclusive_access();
         assert!(mutex_inner.locked);
+        // Withdraw whatever this mutex donated to the outgoing holder,
+        // before handing off to the next owner. Any boost still owed by a
+        // *different* mutex this holder also holds is untouched, since
+        // donations are tagged per-resource_id on the holder's own TCB.
+        if let Some(holder) = mutex_inner.holder_task.take() {
+            Self::withdraw(&holder, self.resource_id);
+        }
+        // Release the banker's Allocation for the outgoing holder; whoever
+        // is woken next re-acquires it via allocate_one_nocheck in lock()
+        MUTEX_BANKER
+            .exclusive_access()
+            .dyn_expand_dealloc(mutex_inner.lock_holder, self.resource_id);
         if let Some(waking_task) = mutex_inner.wait_queue.pop_front() {
+            let next_tid = waking_task.gettid();
+            mutex_inner.lock_holder = next_tid;
+            mutex_inner.holder_task = Some(waking_task.clone());
+            // Waiters left behind now donate to the new holder instead
+            if let Some(top_priority) = mutex_inner.wait_queue.iter().map(|t| t.get_priority()).max() {
+                Self::donate(&waking_task, self.resource_id, top_priority);
+            }
             wakeup_task(waking_task);
         } else {
             mutex_inner.locked = false;
             mutex_inner.lock_holder = usize::MAX;
+            mutex_inner.holder_task = None;
         }
     }
 
@@ -150,3 +281,138 @@ impl Mutex for MutexBlocking {
         }
     }
 }
+
+impl Drop for MutexBlocking {
+    /// Return this mutex's resource id to the free list so a future
+    /// `MutexBlocking::new()` can hand it out again.
+    fn drop(&mut self) {
+        FREE_MUTEX_RESOURCE_IDS.exclusive_access().push(self.resource_id);
+    }
+}
+
+/// Initial number of busy-check iterations `MutexAdaptive` spins for before
+/// falling back to parking. Adjusted per-mutex from observed hold times.
+const DEFAULT_SPIN_LIMIT: usize = 100;
+/// Floor the spin budget never tunes below
+const MIN_SPIN_LIMIT: usize = 8;
+/// Ceiling the spin budget never tunes above
+const MAX_SPIN_LIMIT: usize = 4096;
+
+/// Contention telemetry for one `MutexAdaptive`, analogous to `TcbStatistics`
+#[derive(Clone, Copy, Default)]
+pub struct MutexContentionStats {
+    /// Times the lock was won by spinning, without parking
+    pub spin_successes: usize,
+    /// Times the spin budget ran out and the caller had to park instead
+    pub blocks: usize,
+    /// Total ticks spent waiting (spinning or parked) across every `lock()`
+    pub total_wait_ticks: usize,
+}
+
+/// Mutex that retries a fast path for a bounded, self-tuning number of
+/// iterations, yielding the CPU after each failed attempt, before falling
+/// back to a `MutexBlocking`-style wait queue. An uncontended `lock` takes
+/// the fast path without a context switch; a contended one parks once its
+/// budget runs out
+pub struct MutexAdaptive {
+    locked: UPSafeCell<bool>,
+    inner: UPSafeCell<MutexAdaptiveInner>,
+}
+
+struct MutexAdaptiveInner {
+    wait_queue: VecDeque<Arc<TaskControlBlock>>,
+    spin_limit: usize,
+    /// Tick at which the current holder acquired the lock, used to measure
+    /// hold time for tuning `spin_limit`
+    held_since: usize,
+    stats: MutexContentionStats,
+}
+
+impl MutexAdaptive {
+    /// Create a new adaptive mutex
+    pub fn new() -> Self {
+        trace!("kernel: MutexAdaptive::new");
+        Self {
+            locked: unsafe { UPSafeCell::new(false) },
+            inner: unsafe {
+                UPSafeCell::new(MutexAdaptiveInner {
+                    wait_queue: VecDeque::new(),
+                    spin_limit: DEFAULT_SPIN_LIMIT,
+                    held_since: 0,
+                    stats: MutexContentionStats::default(),
+                })
+            },
+        }
+    }
+
+    /// This mutex's contention telemetry so far
+    pub fn stats(&self) -> MutexContentionStats {
+        self.inner.ro_access().stats
+    }
+
+    /// Narrow or widen `spin_limit` based on how long the lock was actually
+    /// held: a hold much shorter than the current budget means spinning
+    /// less would have paid off, a hold much longer means it's worth
+    /// spinning more before paying for a context switch next time
+    fn tune_from_hold_time(inner: &mut MutexAdaptiveInner, hold_ticks: usize) {
+        if hold_ticks < inner.spin_limit / 2 {
+            inner.spin_limit = (inner.spin_limit / 2).max(MIN_SPIN_LIMIT);
+        } else if hold_ticks > inner.spin_limit * 2 {
+            inner.spin_limit = (inner.spin_limit * 2).min(MAX_SPIN_LIMIT);
+        }
+    }
+}
+
+impl Mutex for MutexAdaptive {
+    fn lock(&self) -> Result<(), LockError> {
+        trace!("kernel: MutexAdaptive::lock");
+        let start = timer::get_time();
+        let spin_limit = self.inner.ro_access().spin_limit;
+        for _ in 0..spin_limit {
+            let mut locked = self.locked.exclusive_access();
+            if !*locked {
+                *locked = true;
+                drop(locked);
+                let mut inner = self.inner.exclusive_access();
+                inner.stats.spin_successes += 1;
+                inner.stats.total_wait_ticks += timer::get_time() - start;
+                inner.held_since = timer::get_time();
+                return Ok(());
+            }
+            drop(locked);
+            // This kernel is single-hart and non-preemptible, so without
+            // yielding here the holder -- which must be a different,
+            // currently-ready task -- never gets the CPU to run its
+            // unlock(), and every contended call would spin out its whole
+            // budget for nothing
+            suspend_current_and_run_next();
+        }
+        // Spin budget exhausted: park and let unlock() hand the lock
+        // straight to us instead of racing every waiter on `locked` again
+        let mut inner = self.inner.exclusive_access();
+        inner.stats.blocks += 1;
+        inner.wait_queue.push_back(current_task().unwrap());
+        drop(inner);
+        block_current_and_run_next();
+        let mut inner = self.inner.exclusive_access();
+        inner.stats.total_wait_ticks += timer::get_time() - start;
+        inner.held_since = timer::get_time();
+        Ok(())
+    }
+
+    fn unlock(&self) {
+        trace!("kernel: MutexAdaptive::unlock");
+        let mut inner = self.inner.exclusive_access();
+        let hold_ticks = timer::get_time() - inner.held_since;
+        Self::tune_from_hold_time(&mut inner, hold_ticks);
+        if let Some(waiting_task) = inner.wait_queue.pop_front() {
+            // Hand off directly: `locked` stays true the whole time, so the
+            // newly woken waiter resumes already holding the lock
+            drop(inner);
+            wakeup_task(waiting_task);
+        } else {
+            drop(inner);
+            *self.locked.exclusive_access() = false;
+        }
+    }
+}
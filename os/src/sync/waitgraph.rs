@@ -0,0 +1,78 @@
+//! Wait-for-graph deadlock detection: builds the graph lazily out of
+//! `held`/`waiting` edges and reports a deadlock only when blocking would
+//! close an actual cycle, rather than running a banker's-algorithm safety
+//! check (which needs `need`/`allocation` matrices and can false-positive
+//! on over-conservative heuristics).
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+/// `held[resource_id]` is the set of tids currently holding that resource
+/// (more than one for a counting semaphore); `waiting[tid]` is the
+/// resource a tid is about to block on. Both maps live behind one lock, so
+/// every check-then-insert pair at a call site is atomic.
+pub struct WaitForGraph {
+    held: BTreeMap<usize, Vec<usize>>,
+    waiting: BTreeMap<usize, usize>,
+}
+
+impl WaitForGraph {
+    /// An empty graph: nothing held, nothing waiting
+    pub fn new() -> Self {
+        Self {
+            held: BTreeMap::new(),
+            waiting: BTreeMap::new(),
+        }
+    }
+
+    /// Would `tid` blocking on `resource_id` close a cycle in the
+    /// wait-for graph? Read-only: callers must still call `mark_waiting`
+    /// themselves, under the same lock hold, to make the check atomic.
+    pub fn would_deadlock(&self, tid: usize, resource_id: usize) -> bool {
+        let mut visited = BTreeSet::new();
+        self.reaches(resource_id, tid, &mut visited)
+    }
+
+    /// DFS: starting from `resource_id`'s holders, following `waiting`
+    /// edges transitively, can we reach `target`?
+    fn reaches(&self, resource_id: usize, target: usize, visited: &mut BTreeSet<usize>) -> bool {
+        let Some(holders) = self.held.get(&resource_id) else {
+            return false;
+        };
+        for &holder in holders {
+            if holder == target {
+                return true;
+            }
+            if !visited.insert(holder) {
+                continue;
+            }
+            if let Some(&next_resource) = self.waiting.get(&holder) {
+                if self.reaches(next_resource, target, visited) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Record that `tid` now holds `resource_id`, no longer waiting on it
+    pub fn acquire(&mut self, tid: usize, resource_id: usize) {
+        self.held.entry(resource_id).or_insert_with(Vec::new).push(tid);
+        self.waiting.remove(&tid);
+    }
+
+    /// Record that `tid` is about to block on `resource_id`
+    pub fn mark_waiting(&mut self, tid: usize, resource_id: usize) {
+        self.waiting.insert(tid, resource_id);
+    }
+
+    /// Record that `tid` released `resource_id`
+    pub fn release(&mut self, tid: usize, resource_id: usize) {
+        if let Some(holders) = self.held.get_mut(&resource_id) {
+            holders.retain(|&holder| holder != tid);
+            if holders.is_empty() {
+                self.held.remove(&resource_id);
+            }
+        }
+    }
+}
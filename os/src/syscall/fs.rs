@@ -1,5 +1,5 @@
 //! File and filesystem-related syscalls
-use crate::fs::{link_file, open_file, unlink_file, OpenFlags, Stat};
+use crate::fs::{link_file, mkdir_dir, open_file, rmdir_dir, unlink_file, OpenFlags, Stat};
 use crate::mm::utils::copy_obj_to_user;
 use crate::mm::{translated_byte_buffer, translated_str, UserBuffer};
 use crate::task::{current_task, current_user_token, read_current_tcb};
@@ -141,3 +141,30 @@ pub fn sys_unlinkat(_name: *const u8) -> isize {
         }
     }
 }
+
+/// Create a subdirectory named by `path`, resolving any leading directory
+/// components first
+pub fn sys_mkdir(path: *const u8) -> isize {
+    trace!("kernel:pid[{}] sys_mkdir", current_task().unwrap().pid.0);
+    let token = current_user_token();
+    let name = translated_str(token, path);
+    match mkdir_dir(name.as_str()) {
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+/// Remove an empty subdirectory named by `path`, resolving any leading
+/// directory components first
+pub fn sys_rmdir(path: *const u8) -> isize {
+    trace!("kernel:pid[{}] sys_rmdir", current_task().unwrap().pid.0);
+    let token = current_user_token();
+    let name = translated_str(token, path);
+    match rmdir_dir(name.as_str()) {
+        Ok(()) => 0,
+        Err(s) => {
+            warn!("rmdir err: {s}");
+            -1
+        }
+    }
+}
@@ -1,10 +1,16 @@
 //! Process management syscalls
 use crate::{
-    config::{CLOCK_FREQ, MAX_SYSCALL_NUM}, mm::{self, utils::copy_obj_to_user}, task::{
-        change_program_brk, exit_current_and_run_next, read_current_tcb, suspend_current_and_run_next, TaskStatus
+    config::{CLOCK_FREQ, MAX_SYSCALL_NUM}, mm::{self, utils::{try_copy_obj_to_user, UAccessError}}, task::{
+        add_task, change_program_brk, current_task, exit_current_and_run_next, read_current_tcb,
+        suspend_current_and_run_next, update_current_tcb, SyscallAction, TaskStatus
     }, timer
 };
 
+/// Translate a failed user-space access into the errno a syscall returns
+fn efault(_err: UAccessError) -> isize {
+    UAccessError::EFAULT
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct TimeVal {
@@ -37,46 +43,46 @@ pub fn sys_yield() -> isize {
     0
 }
 
-/// YOUR JOB: get time with second and microsecond
-/// HINT: You might reimplement it with virtual memory management.
-/// HINT: What if [`TimeVal`] is splitted by two pages ?
+/// Get time with second and microsecond. `-EFAULT` if `_ts` isn't a valid,
+/// writable pointer -- including when the write would straddle two pages,
+/// since `try_copy_obj_to_user` validates and copies per-page.
 pub fn sys_get_time(_ts: *mut TimeVal, _tz: usize) -> isize {
     trace!("kernel: sys_get_time");
     let time_us = timer::get_time_us();
     let sec  = time_us / 1_000_000;
     let usec = time_us % 1_000_000;
-    unsafe {
-        copy_obj_to_user(_ts, &TimeVal {
-            sec, usec,
-        });
+    match try_copy_obj_to_user(_ts, &TimeVal { sec, usec }) {
+        Ok(()) => 0,
+        Err(e) => efault(e),
     }
-    0
 }
 
-/// YOUR JOB: Finish sys_task_info to pass testcases
-/// HINT: You might reimplement it with virtual memory management.
-/// HINT: What if [`TaskInfo`] is splitted by two pages ?
+/// Report the current task's status, per-syscall call counts, and running
+/// time. `-EFAULT` if `_ti` isn't a valid, writable pointer -- including
+/// when the write would straddle two pages.
 pub fn sys_task_info(_ti: *mut TaskInfo) -> isize {
+    trace!("kernel: sys_task_info");
     let mut curr_task  = TaskInfo {
-        status:        TaskStatus::Exited,
+        status:        TaskStatus::Zombie,
         syscall_times: [0; MAX_SYSCALL_NUM],
         time:          0
     };
-    read_current_tcb(|_tid, tcb| {
+    read_current_tcb(&mut |_tid, tcb| {
         curr_task.status = tcb.task_status;
-        curr_task.syscall_times.copy_from_slice(tcb.syscall_times.as_slice());
-        let dtime_ticks = timer::get_time() - tcb.startup_time;
+        curr_task.syscall_times.copy_from_slice(tcb.statistics.syscall_times.as_slice());
+        let dtime_ticks = timer::get_time() - tcb.statistics.startup_time;
         curr_task.time = dtime_ticks * 1000 / CLOCK_FREQ;
     });
-    unsafe { copy_obj_to_user(_ti, &curr_task); }
-    trace!("kernel: sys_task_info");
-    0
+    match try_copy_obj_to_user(_ti, &curr_task) {
+        Ok(()) => 0,
+        Err(e) => efault(e),
+    }
 }
 
 // YOUR JOB: Implement mmap.
-pub fn sys_mmap(start: usize, len: usize, prot: usize) -> isize {
+pub fn sys_mmap(start: usize, len: usize, prot: usize, flags: usize) -> isize {
     trace!("kernel: sys_mmap");
-    mm::utils::mmap_handle::do_mmap(start, len, prot)
+    mm::utils::mmap_handle::do_mmap(start, len, prot, flags)
 }
 
 // YOUR JOB: Implement munmap.
@@ -84,6 +90,67 @@ pub fn sys_munmap(start: usize, len: usize) -> isize {
     trace!("kernel: sys_munmap NOT IMPLEMENTED YET!");
     mm::utils::mmap_handle::do_munmap(start, len)
 }
+
+/// Change permissions on an existing mapping
+pub fn sys_mprotect(start: usize, len: usize, prot: usize) -> isize {
+    trace!("kernel: sys_mprotect");
+    mm::utils::mmap_handle::do_mprotect(start, len, prot)
+}
+
+/// Pin a mapping against reclaim, force-populating any demand-paged pages
+/// in the range first
+pub fn sys_mlock(start: usize, len: usize) -> isize {
+    trace!("kernel: sys_mlock");
+    mm::utils::mmap_handle::do_mlock(start, len)
+}
+
+/// Clear a pin installed by `sys_mlock`
+pub fn sys_munlock(start: usize, len: usize) -> isize {
+    trace!("kernel: sys_munlock");
+    mm::utils::mmap_handle::do_munlock(start, len)
+}
+/// Install a seccomp-style action for `syscall_id` in the current task's
+/// syscall filter. `action` is `0` for Allow, `-1` for Kill, or a small
+/// positive errno magnitude `1..=254` to have the syscall short-circuit
+/// with `-action`. Returns `-1` if `action`/`syscall_id` is out of range or
+/// the filter has already been locked.
+pub fn sys_set_syscall_filter(syscall_id: usize, action: isize) -> isize {
+    trace!("kernel: sys_set_syscall_filter");
+    let action = match action {
+        0 => SyscallAction::Allow,
+        -1 => SyscallAction::Kill,
+        1..=254 => SyscallAction::Errno(-(action as i32)),
+        _ => return -1,
+    };
+    let installed = update_current_tcb(&mut |_pid, tcb| {
+        tcb.syscall_filter.set_action(syscall_id, action)
+    });
+    if installed {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Lock the current task's syscall filter so it can no longer be widened
+pub fn sys_lock_syscall_filter() -> isize {
+    trace!("kernel: sys_lock_syscall_filter");
+    update_current_tcb(&mut |_pid, tcb| tcb.syscall_filter.lock());
+    0
+}
+
+/// Start a new thread in the current process, sharing its address space and
+/// pid with the caller (see `TaskControlBlock::thread_create`). The new
+/// thread begins executing at `entry` with `arg` in its first argument
+/// register, and is queued ready to run immediately. Returns its tid.
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    trace!("kernel: sys_thread_create");
+    let thread = current_task().unwrap().thread_create(entry, arg);
+    let tid = thread.gettid();
+    add_task(thread);
+    tid as isize
+}
+
 /// change data segment size
 pub fn sys_sbrk(size: i32) -> isize {
     trace!("kernel: sys_sbrk");
@@ -0,0 +1,178 @@
+//! ptrace-style debugger syscall: attach to a task, stop it, and peek/poke
+//! its registers and memory.
+//!
+//! Only the caller itself or one of its direct children can be traced, see
+//! `find_related_task` -- there is no global process table in this tree to
+//! reach an arbitrary pid.
+
+use crate::mm::translated_byte_buffer;
+use crate::mm::utils::{copy_obj_from_user, copy_obj_to_user};
+use crate::mm::{VirtAddr, VirtPageNum};
+use crate::task::{current_task, find_related_task, wakeup_task, TaskControlBlock, TaskStatus};
+use crate::trap::TrapContext;
+use alloc::sync::Arc;
+
+/// Check that every page covering one word at `addr` is mapped and, if
+/// `write` is set, writable in `tracee`'s address space -- the tracee-side
+/// equivalent of `mm::utils::validate_user_range`, which only ever looks at
+/// the *current* task and so can't be reused to validate a traced task
+fn tracee_word_mapped(tracee: &Arc<TaskControlBlock>, addr: usize, write: bool) -> bool {
+    let lo = addr & !0xFFF;
+    let hi = (addr + core::mem::size_of::<usize>() + 0xFFF) & !0xFFF;
+    let inner = tracee.inner_exclusive_access();
+    let memory_set = inner.memory_set.exclusive_access();
+    let mut va = lo;
+    while va < hi {
+        let vpn = VirtPageNum::from(VirtAddr::from(va));
+        let Some(pte) = memory_set.translate(vpn) else {
+            return false;
+        };
+        if !pte.is_valid() || !pte.readable() || (write && !pte.writable()) {
+            return false;
+        }
+        va += 0x1000;
+    }
+    true
+}
+
+/// Ask to be traced by the parent; takes effect the next time the parent
+/// calls `PTRACE_ATTACH` or simply reads this task, since there is no
+/// separate "stop at next syscall" handshake in this tree
+pub const PTRACE_TRACEME: usize = 0;
+/// Attach to `pid` as its tracer
+pub const PTRACE_ATTACH: usize = 1;
+/// Read one word at `addr` in the tracee's address space, returned as the
+/// syscall's return value
+pub const PTRACE_PEEKDATA: usize = 2;
+/// Write `data` as one word at `addr` in the tracee's address space
+pub const PTRACE_POKEDATA: usize = 3;
+/// Copy the tracee's saved `TrapContext` to the `*mut TrapContext` in `data`
+pub const PTRACE_GETREGS: usize = 4;
+/// Overwrite the tracee's saved `TrapContext` from the `*const TrapContext` in `data`
+pub const PTRACE_SETREGS: usize = 5;
+/// Let the tracee run until its next stop
+pub const PTRACE_CONT: usize = 6;
+/// Let the tracee run for a single instruction, then stop it again
+///
+/// YOUR JOB: actual single-instruction stepping needs a trap-return hook
+/// that isn't wired up in this tree; this currently behaves like `PTRACE_CONT`
+pub const PTRACE_SINGLESTEP: usize = 7;
+
+/// Read one word of the tracee's memory at `addr`. `None` if any page in
+/// the word's range is unmapped in the tracee's address space.
+fn peek_word(tracee: &Arc<TaskControlBlock>, addr: usize) -> Option<usize> {
+    if !tracee_word_mapped(tracee, addr, false) {
+        return None;
+    }
+    let token = tracee.get_user_token();
+    let mut bytes = [0u8; core::mem::size_of::<usize>()];
+    let mut begin = 0;
+    for phys in translated_byte_buffer(token, addr as *const u8, bytes.len()) {
+        let end = begin + phys.len();
+        bytes[begin..end].copy_from_slice(phys);
+        begin = end;
+    }
+    Some(usize::from_ne_bytes(bytes))
+}
+
+/// Write one word of the tracee's memory at `addr`. Returns `false` if any
+/// page in the word's range is unmapped or not writable in the tracee's
+/// address space, without writing anything.
+fn poke_word(tracee: &Arc<TaskControlBlock>, addr: usize, value: usize) -> bool {
+    if !tracee_word_mapped(tracee, addr, true) {
+        return false;
+    }
+    let token = tracee.get_user_token();
+    let bytes = value.to_ne_bytes();
+    let mut begin = 0;
+    for phys in translated_byte_buffer(token, addr as *const u8, bytes.len()) {
+        let end = begin + phys.len();
+        phys.copy_from_slice(&bytes[begin..end]);
+        begin = end;
+    }
+    true
+}
+
+/// `sys_ptrace(request, pid, addr, data)`: inspect or control the task
+/// `pid`, one of `PTRACE_*` above. `addr`/`data` are interpreted per
+/// request, as in real `ptrace(2)`.
+pub fn sys_ptrace(request: usize, pid: usize, addr: usize, data: usize) -> isize {
+    trace!(
+        "kernel: sys_ptrace request={} pid={} addr={:#x} data={:#x}",
+        request,
+        pid,
+        addr,
+        data
+    );
+    match request {
+        PTRACE_TRACEME => {
+            let me = current_task().unwrap();
+            let mut inner = me.inner_exclusive_access();
+            let Some(parent) = inner.parent.clone() else {
+                return -1;
+            };
+            inner.tracer = Some(parent);
+            0
+        }
+        PTRACE_ATTACH => {
+            let Some(tracee) = find_related_task(pid) else {
+                return -1;
+            };
+            tracee.inner_exclusive_access().tracer = Some(Arc::downgrade(&current_task().unwrap()));
+            0
+        }
+        PTRACE_PEEKDATA => {
+            let Some(tracee) = find_related_task(pid) else {
+                return -1;
+            };
+            match peek_word(&tracee, addr) {
+                Some(word) => word as isize,
+                None => -1,
+            }
+        }
+        PTRACE_POKEDATA => {
+            let Some(tracee) = find_related_task(pid) else {
+                return -1;
+            };
+            if poke_word(&tracee, addr, data) {
+                0
+            } else {
+                -1
+            }
+        }
+        PTRACE_GETREGS => {
+            let Some(tracee) = find_related_task(pid) else {
+                return -1;
+            };
+            let trap_cx = *tracee.inner_exclusive_access().get_trap_cx();
+            unsafe {
+                copy_obj_to_user(data as *mut TrapContext, &trap_cx);
+            }
+            0
+        }
+        PTRACE_SETREGS => {
+            let Some(tracee) = find_related_task(pid) else {
+                return -1;
+            };
+            let mut inner = tracee.inner_exclusive_access();
+            let mut trap_cx = *inner.get_trap_cx();
+            copy_obj_from_user(&mut trap_cx, data as *const TrapContext);
+            *inner.get_trap_cx() = trap_cx;
+            0
+        }
+        PTRACE_CONT | PTRACE_SINGLESTEP => {
+            let Some(tracee) = find_related_task(pid) else {
+                return -1;
+            };
+            let mut inner = tracee.inner_exclusive_access();
+            if inner.task_status != TaskStatus::TraceStopped {
+                return -1;
+            }
+            inner.task_status = TaskStatus::Ready;
+            drop(inner);
+            wakeup_task(tracee);
+            0
+        }
+        _ => -1,
+    }
+}
@@ -0,0 +1,89 @@
+//! Scheduler-related syscalls: nice-style priority and scheduling policy
+//! getters/setters, backed by `SchedInfo` on the current task's TCB.
+//!
+//! Policy storage only: `sys_sched_setscheduler`/`sys_sched_getscheduler`
+//! round-trip the policy through `SchedInfo`, but nothing yet makes
+//! `task::manager::fetch_task` actually pick `Fifo`/`RoundRobin` tasks
+//! ahead of `Normal` ones, or re-queue a `RoundRobin` task on a timer tick
+//! instead of a natural yield. `manager.rs` isn't part of this source
+//! tree, so that scheduling behavior can't be added from what's checked
+//! in here -- until it is, `sys_sched_setscheduler(SCHED_FIFO, ...)`
+//! changes what's stored but not how the task is actually scheduled.
+
+use crate::task::{current_task, SchedulerPolicy};
+
+/// Stride-scheduled, the default: priority governs its pass
+pub const SCHED_NORMAL: usize = 0;
+/// Runs until it blocks or yields; never preempted by a timer tick
+pub const SCHED_FIFO: usize = 1;
+/// Like `SCHED_FIFO`, but re-queued behind other `SCHED_RR` tasks on a
+/// timer tick instead of keeping the CPU
+pub const SCHED_RR: usize = 2;
+
+fn policy_from_usize(policy: usize) -> Option<SchedulerPolicy> {
+    match policy {
+        SCHED_NORMAL => Some(SchedulerPolicy::Normal),
+        SCHED_FIFO => Some(SchedulerPolicy::Fifo),
+        SCHED_RR => Some(SchedulerPolicy::RoundRobin),
+        _ => None,
+    }
+}
+
+fn policy_to_usize(policy: SchedulerPolicy) -> usize {
+    match policy {
+        SchedulerPolicy::Normal => SCHED_NORMAL,
+        SchedulerPolicy::Fifo => SCHED_FIFO,
+        SchedulerPolicy::RoundRobin => SCHED_RR,
+    }
+}
+
+/// Set the current task's nice-style priority. Only meaningful under
+/// `SCHED_NORMAL`, where it's translated into a stride-scheduling pass
+/// (`BIG_STRIDE / priority`, clamped to `SchedInfo::{MIN,MAX}_PRIORITY`).
+pub fn sys_setpriority(prio: isize) -> isize {
+    trace!("kernel: sys_setpriority prio={}", prio);
+    if prio <= 0 {
+        return -1;
+    }
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    inner.sched_info.set_own_priority(prio as usize);
+    inner.recompute_priority();
+    0
+}
+
+/// The current task's priority
+pub fn sys_getpriority() -> isize {
+    trace!("kernel: sys_getpriority");
+    let task = current_task().unwrap();
+    task.inner_exclusive_access().sched_info.get_priority() as isize
+}
+
+/// Set the current task's scheduling policy (`SCHED_NORMAL`/`SCHED_FIFO`/
+/// `SCHED_RR`) and, for `SCHED_NORMAL`, its priority
+pub fn sys_sched_setscheduler(policy: usize, prio: usize) -> isize {
+    trace!(
+        "kernel: sys_sched_setscheduler policy={} prio={}",
+        policy,
+        prio
+    );
+    let Some(policy) = policy_from_usize(policy) else {
+        return -1;
+    };
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    inner.sched_info.set_policy(policy);
+    if policy == SchedulerPolicy::Normal && prio > 0 {
+        inner.sched_info.set_own_priority(prio);
+        inner.recompute_priority();
+    }
+    0
+}
+
+/// The current task's scheduling policy, as one of `SCHED_NORMAL`/
+/// `SCHED_FIFO`/`SCHED_RR`
+pub fn sys_sched_getscheduler() -> isize {
+    trace!("kernel: sys_sched_getscheduler");
+    let task = current_task().unwrap();
+    policy_to_usize(task.inner_exclusive_access().sched_info.get_policy()) as isize
+}
@@ -0,0 +1,94 @@
+//! Signal-related syscalls: kill, sigaction, sigprocmask, sigreturn
+
+use crate::mm::utils::{try_copy_obj_from_user, try_copy_obj_to_user, UAccessError};
+use crate::task::signal::{is_uncatchable, MAX_SIG};
+use crate::task::{current_task, find_related_task, wakeup_task, SignalAction, SignalFlags, TaskStatus};
+
+/// Translate a failed user-space access into the errno a syscall returns
+fn efault(_err: UAccessError) -> isize {
+    UAccessError::EFAULT
+}
+
+/// Send signal `signum` to `pid`. Only the caller itself or one of its
+/// direct children can currently be targeted, see `find_related_task`.
+pub fn sys_kill(pid: usize, signum: i32) -> isize {
+    trace!("kernel: sys_kill pid={} signum={}", pid, signum);
+    if signum < 0 || signum as usize >= MAX_SIG {
+        return -1;
+    }
+    let Some(target) = find_related_task(pid) else {
+        return -1;
+    };
+    let mut inner = target.inner_exclusive_access();
+    if !inner.signals.raise(signum as usize) {
+        return -1;
+    }
+    let blocked = inner.task_status == TaskStatus::Blocked;
+    drop(inner);
+    if blocked {
+        wakeup_task(target);
+    }
+    0
+}
+
+/// Install a new handler for `signum`, returning the previous one through
+/// `old_action` if it is non-null. Fails for `SIGKILL`/`SIGSTOP`, which
+/// cannot be caught. `-EFAULT` if `action`/`old_action` isn't a valid
+/// pointer for its respective direction.
+pub fn sys_sigaction(
+    signum: i32,
+    action: *const SignalAction,
+    old_action: *mut SignalAction,
+) -> isize {
+    trace!("kernel: sys_sigaction signum={}", signum);
+    if signum < 0 || signum as usize >= MAX_SIG || is_uncatchable(signum as usize) {
+        return -1;
+    }
+    let task = current_task().unwrap();
+    if !old_action.is_null() {
+        let old = task
+            .inner_exclusive_access()
+            .signals
+            .actions
+            .get(signum as usize)
+            .unwrap_or_default();
+        if let Err(e) = try_copy_obj_to_user(old_action, &old) {
+            return efault(e);
+        }
+    }
+    let mut new_action = SignalAction::default();
+    if let Err(e) = try_copy_obj_from_user(&mut new_action, action) {
+        return efault(e);
+    }
+    if task
+        .inner_exclusive_access()
+        .signals
+        .actions
+        .set(signum as usize, new_action)
+    {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Replace the current task's blocked-signal mask with `mask`, returning
+/// the previous mask. `SIGKILL`/`SIGSTOP` can never be blocked, the same
+/// way they can never be caught by `sys_sigaction`.
+pub fn sys_sigprocmask(mask: u32) -> isize {
+    trace!("kernel: sys_sigprocmask mask={:#x}", mask);
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let old = inner.signals.mask.bits();
+    inner.signals.mask =
+        SignalFlags::from_bits_truncate(mask) & !(SignalFlags::SIGKILL | SignalFlags::SIGSTOP);
+    old as isize
+}
+
+/// Restore the trap context saved before the currently-running signal
+/// handler was dispatched. Returns `-1` if no handler is running.
+pub fn sys_sigreturn() -> isize {
+    trace!("kernel: sys_sigreturn");
+    let task = current_task().unwrap();
+    task.inner_exclusive_access().sigreturn()
+}
@@ -1,20 +1,40 @@
 
-use crate::sync::{Condvar, Mutex, MutexBlocking, MutexSpin};
-use crate::task::{block_current_and_run_next, current_process, current_task, update_current_tcb, TaskStatus};
+use crate::sync::{
+    futex::{futex_wait, futex_wake, FUTEX_WAIT, FUTEX_WAKE},
+    waitgraph::WaitForGraph,
+    Condvar, Mutex, MutexBlocking, MutexSpin, UPSafeCell,
+};
+use crate::task::{block_current_and_run_next, current_process, current_task, update_current_tcb};
 use crate::timer::{add_timer, get_time_ms};
+use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
+use lazy_static::*;
+
+lazy_static! {
+    /// Wait-for graphs tracking which tid holds/waits-on which semaphore,
+    /// consulted by `sys_semaphore_down` when `trace_deadlock` is set.
+    ///
+    /// `sem_id` is only unique within a process's own `semaphore_list`
+    /// (like `sem_banker` used to be before the wait-for graph replaced
+    /// it), so this is keyed by pid rather than being a single shared
+    /// graph -- otherwise two processes that each allocate their own
+    /// semaphore 0 would alias the same held/waiting edges.
+    static ref SEM_WAITGRAPHS: UPSafeCell<BTreeMap<usize, WaitForGraph>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Run `f` against the calling process's wait-for graph, creating it on
+/// first use.
+fn with_sem_waitgraph<R>(pid: usize, f: impl FnOnce(&mut WaitForGraph) -> R) -> R {
+    let mut graphs = SEM_WAITGRAPHS.exclusive_access();
+    f(graphs.entry(pid).or_insert_with(WaitForGraph::new))
+}
 /// sleep syscall
 pub fn sys_sleep(ms: usize) -> isize {
     trace!(
         "kernel:pid[{}] tid[{}] sys_sleep",
         current_task().unwrap().process.upgrade().unwrap().getpid(),
-        current_task()
-            .unwrap()
-            .inner_exclusive_access()
-            .res
-            .as_ref()
-            .unwrap()
-            .tid
+        current_task().unwrap().gettid()
     );
     let expire_ms = get_time_ms() + ms;
     let task = current_task().unwrap();
@@ -27,19 +47,16 @@ pub fn sys_mutex_create(blocking: bool) -> isize {
     trace!(
         "kernel:pid[{}] tid[{}] sys_mutex_create",
         current_task().unwrap().process.upgrade().unwrap().getpid(),
-        current_task()
-            .unwrap()
-            .inner_exclusive_access()
-            .res
-            .as_ref()
-            .unwrap()
-            .tid
+        current_task().unwrap().gettid()
     );
     let process = current_process();
     let mutex: Option<Arc<dyn Mutex>> = if !blocking {
         Some(Arc::new(MutexSpin::new()))
     } else {
-        Some(Arc::new(MutexBlocking::new()))
+        match MutexBlocking::new() {
+            Some(mutex) => Some(Arc::new(mutex)),
+            None => return -1,
+        }
     };
     let mut process_inner = process.inner_exclusive_access();
     if let Some(id) = process_inner
@@ -61,37 +78,24 @@ pub fn sys_mutex_lock(mutex_id: usize) -> isize {
     trace!(
         "kernel:pid[{}] tid[{}] sys_mutex_lock",
         current_task().unwrap().process.upgrade().unwrap().getpid(),
-        current_task()
-            .unwrap()
-            .inner_exclusive_access()
-            .res
-            .as_ref()
-            .unwrap()
-            .tid
+        current_task().unwrap().gettid()
     );
     let process = current_process();
     let process_inner = process.inner_exclusive_access();
     let mutex = Arc::clone(process_inner.mutex_list[mutex_id].as_ref().unwrap());
     drop(process_inner);
     drop(process);
-    if mutex.try_trace_lock_is_dead() {
-        return -0xDEAD;
+    match mutex.lock() {
+        Ok(()) => 0,
+        Err(_) => -0xDEAD,
     }
-    mutex.lock();
-    0
 }
 /// mutex unlock syscall
 pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
     trace!(
         "kernel:pid[{}] tid[{}] sys_mutex_unlock",
         current_task().unwrap().process.upgrade().unwrap().getpid(),
-        current_task()
-            .unwrap()
-            .inner_exclusive_access()
-            .res
-            .as_ref()
-            .unwrap()
-            .tid
+        current_task().unwrap().gettid()
     );
     let process = current_process();
     let process_inner = process.inner_exclusive_access();
@@ -106,13 +110,7 @@ pub fn sys_semaphore_create(res_count: usize) -> isize {
     trace!(
         "kernel:pid[{}] tid[{}] sys_semaphore_create",
         current_task().unwrap().process.upgrade().unwrap().getpid(),
-        current_task()
-            .unwrap()
-            .inner_exclusive_access()
-            .res
-            .as_ref()
-            .unwrap()
-            .tid
+        current_task().unwrap().gettid()
     );
     let process = current_process();
     match process.new_semaphore(res_count) {
@@ -122,7 +120,7 @@ pub fn sys_semaphore_create(res_count: usize) -> isize {
 }
 /// semaphore up syscall
 pub fn sys_semaphore_up(sem_id: usize) -> isize {
-    let tid = current_task().unwrap().gettid().unwrap();
+    let tid = current_task().unwrap().gettid();
     let pid = current_process().getpid();
     trace!(
         "kernel:pid[{}] tid[{}] sys_semaphore_up", pid, tid
@@ -130,9 +128,8 @@ pub fn sys_semaphore_up(sem_id: usize) -> isize {
     let process = current_process();
     let process_inner = process.inner_exclusive_access();
     let sem = Arc::clone(process_inner.semaphore_list[sem_id].as_ref().unwrap());
-    let banker = process_inner.sem_banker.clone();
     drop(process_inner);
-    banker.exclusive_access().dyn_expand_dealloc(tid, sem.sem_id);
+    with_sem_waitgraph(pid, |graph| graph.release(tid, sem_id));
     sem.up();
     warn!(
         "kernel:pid[{}] tid[{}] sys_semaphore_up (sem:) {}", pid, tid, sem_id
@@ -140,8 +137,14 @@ pub fn sys_semaphore_up(sem_id: usize) -> isize {
     0
 }
 /// semaphore down syscall
+///
+/// When `trace_deadlock` is set, checks for deadlock by building a
+/// wait-for graph out of who holds and who is about to wait on each
+/// semaphore, and refusing to block only when doing so would actually
+/// close a cycle back to this tid -- not on a banker's-algorithm safety
+/// check, which false-positives on interleavings that can't deadlock.
 pub fn sys_semaphore_down(sem_id: usize) -> isize {
-    let tid = current_task().unwrap().gettid().unwrap();
+    let tid = current_task().unwrap().gettid();
     let pid = current_process().getpid();
     trace!(
         "kernel:pid[{}] tid[{}] sys_semaphore_down", pid, tid
@@ -149,34 +152,30 @@ pub fn sys_semaphore_down(sem_id: usize) -> isize {
     let process = current_process();
     let process_inner = process.inner_exclusive_access();
     let sem = Arc::clone(process_inner.semaphore_list[sem_id].as_ref().unwrap());
-    let banker = process_inner.sem_banker.clone();
-    banker.exclusive_access().need[tid][sem_id] += 1;
-    if process_inner.trace_deadlock {
+    let trace_deadlock = process_inner.trace_deadlock;
+    drop(process_inner);
+    if trace_deadlock {
         warn!(
             "kernel:pid[{}] tid[{}] Detecting DeadLock...", pid, tid
         );
-        if !banker.exclusive_access().is_safe() {
-            banker.exclusive_access().need[tid][sem_id] -= 1;
-            warn!("kernel:pid[{}] tid[{}] semaphore {} DEAD LOCK", pid, tid, sem_id);
-            return -0xDEAD;
-        }
-        let mut cnt = 0;
-        for t in &process_inner.tasks {
-            match t {
-                Some(thrd) => { if thrd.inner_ro_access().task_status == TaskStatus::Blocked { cnt += 1; } },
-                None => {}
+        let deadlock = with_sem_waitgraph(pid, |graph| {
+            if graph.would_deadlock(tid, sem_id) {
+                true
+            } else {
+                graph.mark_waiting(tid, sem_id);
+                false
             }
-        }
-        if cnt >= process_inner.thread_count() - 1 {
+        });
+        if deadlock {
+            warn!("kernel:pid[{}] tid[{}] semaphore {} DEAD LOCK", pid, tid, sem_id);
             return -0xDEAD;
         }
         warn!(
             "kernel:pid[{}] tid[{}] sem_id[{}] NO Dead Lock detected", pid, tid, sem_id
         );
     }
-    drop(process_inner);
     let ret = sem.down();
-    banker.exclusive_access().allocate_one_nocheck(tid, sem_id);
+    with_sem_waitgraph(pid, |graph| graph.acquire(tid, sem_id));
     if ret { 0 } else { -0xDEAD }
 }
 /// condvar create syscall
@@ -184,13 +183,7 @@ pub fn sys_condvar_create() -> isize {
     trace!(
         "kernel:pid[{}] tid[{}] sys_condvar_create",
         current_task().unwrap().process.upgrade().unwrap().getpid(),
-        current_task()
-            .unwrap()
-            .inner_exclusive_access()
-            .res
-            .as_ref()
-            .unwrap()
-            .tid
+        current_task().unwrap().gettid()
     );
     let process = current_process();
     let mut process_inner = process.inner_exclusive_access();
@@ -216,13 +209,7 @@ pub fn sys_condvar_signal(condvar_id: usize) -> isize {
     trace!(
         "kernel:pid[{}] tid[{}] sys_condvar_signal",
         current_task().unwrap().process.upgrade().unwrap().getpid(),
-        current_task()
-            .unwrap()
-            .inner_exclusive_access()
-            .res
-            .as_ref()
-            .unwrap()
-            .tid
+        current_task().unwrap().gettid()
     );
     let process = current_process();
     let process_inner = process.inner_exclusive_access();
@@ -236,21 +223,32 @@ pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
     trace!(
         "kernel:pid[{}] tid[{}] sys_condvar_wait",
         current_task().unwrap().process.upgrade().unwrap().getpid(),
-        current_task()
-            .unwrap()
-            .inner_exclusive_access()
-            .res
-            .as_ref()
-            .unwrap()
-            .tid
+        current_task().unwrap().gettid()
     );
     let process = current_process();
     let process_inner = process.inner_exclusive_access();
     let condvar = Arc::clone(process_inner.condvar_list[condvar_id].as_ref().unwrap());
     let mutex = Arc::clone(process_inner.mutex_list[mutex_id].as_ref().unwrap());
     drop(process_inner);
-    condvar.wait(mutex);
-    0
+    match condvar.wait(mutex) {
+        Ok(()) => 0,
+        Err(_) => -0xDEAD,
+    }
+}
+/// futex syscall: `FUTEX_WAIT` blocks while `*uaddr == val`, `FUTEX_WAKE`
+/// wakes up to `val` waiters; waiters are keyed by the word's physical
+/// address so futexes work across shared memory between processes
+pub fn sys_futex(uaddr: *mut u32, op: usize, val: u32) -> isize {
+    trace!(
+        "kernel:pid[{}] sys_futex op={}",
+        current_process().getpid(),
+        op
+    );
+    match op {
+        FUTEX_WAIT => futex_wait(uaddr, val),
+        FUTEX_WAKE => futex_wake(uaddr, val as usize),
+        _ => -22, // -EINVAL
+    }
 }
 /// enable deadlock detection syscall
 ///
@@ -258,13 +256,7 @@ pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
 pub fn sys_enable_deadlock_detect(_enabled: usize) -> isize {
     trace!("kernel: sys_enable_deadlock_detect in process[{}] thread[{}]",
         current_task().unwrap().process.upgrade().unwrap().getpid(),
-        current_task()
-            .unwrap()
-            .inner_exclusive_access()
-            .res
-            .as_ref()
-            .unwrap()
-            .tid);
+        current_task().unwrap().gettid());
     update_current_tcb(|pcb, _itcb| {
         pcb.inner_exclusive_access().trace_deadlock = _enabled != 0;
     });
@@ -17,6 +17,7 @@ mod context;
 mod id;
 mod manager;
 mod processor;
+pub mod signal;
 mod switch;
 #[allow(clippy::module_inception)]
 pub mod task;
@@ -29,7 +30,8 @@ use lazy_static::*;
 pub use manager::{fetch_task, TaskManager};
 use switch::__switch;
 use task::TaskControlBlockInner;
-pub use task::{TaskControlBlock, TaskStatus};
+pub use signal::{SignalAction, SignalFlags};
+pub use task::{SchedulerPolicy, SyscallAction, TaskControlBlock, TaskStatus};
 
 pub use context::TaskContext;
 pub use id::{kstack_alloc, pid_alloc, KernelStack, PidHandle};
@@ -77,6 +79,19 @@ pub fn exit_current_and_run_next(exit_code: i32) {
         panic!("All applications completed!");
     }
 
+    // If a tracer is attached, stop here so it can inspect this task one
+    // last time before it becomes a zombie; parked off the ready queue like
+    // `block_current_and_run_next`, resuming (and falling through to the
+    // real exit below) once the tracer issues `PTRACE_CONT`.
+    {
+        let mut inner = task.inner_exclusive_access();
+        if inner.stop_for_tracer() {
+            let task_cx_ptr = &mut inner.task_cx as *mut TaskContext;
+            drop(inner);
+            schedule(task_cx_ptr);
+        }
+    }
+
     // **** access current TCB exclusively
     let mut inner = task.inner_exclusive_access();
     // Change status to Zombie
@@ -96,7 +111,7 @@ pub fn exit_current_and_run_next(exit_code: i32) {
 
     inner.children.clear();
     // deallocate user space
-    inner.memory_set.recycle_data_pages();
+    inner.memory_set.exclusive_access().recycle_data_pages();
     drop(inner);
     // **** release current PCB
     // drop task manually to maintain rc correctly
@@ -122,6 +137,24 @@ pub fn get_current_pid()-> usize {
     current_task().unwrap().getpid()
 }
 
+/// Find the task for `pid` among the current task and its direct children.
+///
+/// There is no global process table in this tree to reach an arbitrary
+/// pid anywhere in the system, so `sys_kill` can only target the caller or
+/// one of its own children; restoring `TASK_MANAGER`'s process registry
+/// would lift that restriction.
+pub fn find_related_task(pid: usize) -> Option<Arc<TaskControlBlock>> {
+    let me = current_task()?;
+    if me.getpid() == pid {
+        return Some(me);
+    }
+    me.inner_exclusive_access()
+        .children
+        .iter()
+        .find(|child| child.getpid() == pid)
+        .cloned()
+}
+
 /// Update current TCB with function updatef().
 pub fn update_current_tcb<T, RetT>(updatef: &mut T)-> RetT
     where T: FnMut(&PidHandle, &mut TaskControlBlockInner)-> RetT {
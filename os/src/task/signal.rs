@@ -0,0 +1,182 @@
+//! POSIX-style signal bitmask, per-signal handler table, and the
+//! dispatch/return logic run at the kernel/user trap boundary.
+//!
+//! Signals are enqueued on the target TCB by `sys_kill` and dispatched the
+//! next time that task is about to return to user mode (mirroring the
+//! Starnix model: signals live on the task, not on a separate delivery
+//! queue), rather than being delivered synchronously across tasks.
+
+use crate::trap::TrapContext;
+
+bitflags! {
+    /// A set of pending or blocked signals; signal number `n` occupies bit `n`
+    #[derive(Default)]
+    pub struct SignalFlags: u32 {
+        /// hangup
+        const SIGHUP    = 1 << 1;
+        /// interrupt
+        const SIGINT    = 1 << 2;
+        /// quit
+        const SIGQUIT   = 1 << 3;
+        /// illegal instruction
+        const SIGILL    = 1 << 4;
+        /// trace trap
+        const SIGTRAP   = 1 << 5;
+        /// abort
+        const SIGABRT   = 1 << 6;
+        /// bus error
+        const SIGBUS    = 1 << 7;
+        /// floating point exception
+        const SIGFPE    = 1 << 8;
+        /// kill, cannot be caught, blocked, or ignored
+        const SIGKILL   = 1 << 9;
+        /// user-defined signal 1
+        const SIGUSR1   = 1 << 10;
+        /// segmentation fault
+        const SIGSEGV   = 1 << 11;
+        /// user-defined signal 2
+        const SIGUSR2   = 1 << 12;
+        /// broken pipe
+        const SIGPIPE   = 1 << 13;
+        /// alarm clock
+        const SIGALRM   = 1 << 14;
+        /// termination
+        const SIGTERM   = 1 << 15;
+        /// child status changed
+        const SIGCHLD   = 1 << 17;
+        /// continue
+        const SIGCONT   = 1 << 18;
+        /// stop, cannot be caught, blocked, or ignored
+        const SIGSTOP   = 1 << 19;
+    }
+}
+
+/// One past the highest signal number `SignalActions` has a slot for
+pub const MAX_SIG: usize = 32;
+
+/// Signal numbers whose default action (no handler installed) terminates
+/// the task, instead of being ignored
+fn is_default_fatal(signum: usize) -> bool {
+    matches!(signum, 1 | 2 | 3 | 4 | 6 | 7 | 8 | 9 | 11 | 13 | 14 | 15)
+}
+
+/// Signal numbers that can never be caught, blocked, or ignored
+pub fn is_uncatchable(signum: usize) -> bool {
+    matches!(signum, 9 | 19) // SIGKILL, SIGSTOP
+}
+
+/// The lowest-numbered signal set in `flags`, if any
+fn lowest_signum(flags: SignalFlags) -> Option<usize> {
+    (0..MAX_SIG as u32).find(|bit| flags.bits() & (1 << bit) != 0)
+}
+
+/// A registered handler for one signal: entry point and the additional
+/// signals to block while it runs
+#[derive(Clone, Copy)]
+pub struct SignalAction {
+    /// Address of the user-space handler function
+    pub handler: usize,
+    /// Signals to add to the blocked mask for the duration of this handler
+    pub mask: SignalFlags,
+}
+
+impl Default for SignalAction {
+    fn default() -> Self {
+        Self {
+            handler: 0,
+            mask: SignalFlags::empty(),
+        }
+    }
+}
+
+/// Per-task table of installed signal handlers
+#[derive(Clone, Copy)]
+pub struct SignalActions {
+    table: [SignalAction; MAX_SIG],
+}
+
+impl Default for SignalActions {
+    fn default() -> Self {
+        Self {
+            table: [SignalAction::default(); MAX_SIG],
+        }
+    }
+}
+
+impl SignalActions {
+    /// The installed handler for `signum`, if any (a `handler` of `0` means
+    /// none is installed)
+    pub fn get(&self, signum: usize) -> Option<SignalAction> {
+        self.table
+            .get(signum)
+            .filter(|action| action.handler != 0)
+            .copied()
+    }
+
+    /// Install `action` as the handler for `signum`. Fails for `SIGKILL`
+    /// and `SIGSTOP`, which cannot be caught.
+    pub fn set(&mut self, signum: usize, action: SignalAction) -> bool {
+        if signum >= MAX_SIG || is_uncatchable(signum) {
+            return false;
+        }
+        self.table[signum] = action;
+        true
+    }
+}
+
+/// Signal-related state that lives on `TaskControlBlockInner`
+#[derive(Default)]
+pub struct SignalState {
+    /// Signals delivered but not yet dispatched to user space
+    pub pending: SignalFlags,
+    /// Signals currently blocked from dispatch
+    pub mask: SignalFlags,
+    /// The signal whose handler is currently running, if any
+    pub handling_sig: Option<usize>,
+    /// Installed handlers
+    pub actions: SignalActions,
+    /// The trap context saved when a handler was dispatched, restored by
+    /// `sys_sigreturn`
+    pub trap_ctx_backup: Option<TrapContext>,
+}
+
+impl SignalState {
+    /// Mark `signum` pending; cannot be suppressed even while blocked
+    /// (blocking only delays dispatch, matching POSIX `sigprocmask`)
+    pub fn raise(&mut self, signum: usize) -> bool {
+        match SignalFlags::from_bits(1 << signum) {
+            Some(flag) => {
+                self.pending |= flag;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Find the next pending, unblocked signal that should be dispatched
+    /// or terminate the task, clearing its pending bit. Returns `Ok(exit
+    /// code)` if this signal's default action should terminate the task
+    /// (no handler installed), or `Err((signum, action))` if a handler
+    /// should be run.
+    ///
+    /// Callers should invoke this right before `trap_return` restores the
+    /// user trap context: on `Err((signum, action))`, back up the current
+    /// `TrapContext`, point `sepc` at `action.handler` and `a0` at
+    /// `signum`; on `Ok(code)`, call `exit_current_and_run_next(code)`
+    /// instead of resuming user mode.
+    pub fn next_dispatch(&mut self) -> Option<Result<i32, (usize, SignalAction)>> {
+        loop {
+            let deliverable = self.pending & !self.mask;
+            let signum = lowest_signum(deliverable)?;
+            let flag = SignalFlags::from_bits(1 << signum).unwrap();
+            self.pending.remove(flag);
+            match self.actions.get(signum) {
+                Some(action) => return Some(Err((signum, action))),
+                None if is_default_fatal(signum) => {
+                    return Some(Ok(-(signum as i32)));
+                }
+                None => continue,
+            }
+        }
+    }
+}
@@ -1,8 +1,10 @@
 //! Types related to task management & Functions for completely changing TCB
+use super::signal::SignalState;
 use super::TaskContext;
 use super::{kstack_alloc, pid_alloc, KernelStack, PidHandle};
-use crate::config::{MAX_SYSCALL_NUM, TRAP_CONTEXT_BASE};
-use crate::mm::{MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE};
+use crate::config::{MAX_SYSCALL_NUM, PAGE_SIZE, TRAP_CONTEXT_BASE, USER_STACK_SIZE};
+use crate::mm::utils::mmap_handle::LazyMmapRegion;
+use crate::mm::{MapPermission, MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE};
 use crate::sync::UPSafeCell;
 use crate::timer;
 use crate::trap::{trap_handler, TrapContext};
@@ -11,13 +13,20 @@ use alloc::vec::Vec;
 use core::cell::{Ref, RefMut};
 use core::cmp::Ordering;
 
+/// Spacing between two threads' user stacks, leaving one guard page between
+/// them so a stack overflow faults instead of corrupting its neighbour
+const THREAD_USTACK_STRIDE: usize = USER_STACK_SIZE + PAGE_SIZE;
+
 /// Task control block structure
 ///
 /// Directly save the contents that will not change during running
 pub struct TaskControlBlock {
     // Immutable
-    /// Process identifier
-    pub pid: PidHandle,
+    /// Process identifier. An `Arc` so every thread of one process (see
+    /// `thread_create`) can share the same pid instead of each allocating
+    /// its own -- the pid is only actually returned to the allocator once
+    /// the last thread sharing it drops.
+    pub pid: Arc<PidHandle>,
 
     /// Kernel stack corresponding to PID
     pub kernel_stack: KernelStack,
@@ -38,12 +47,23 @@ impl TaskControlBlock {
     /// Get the address of app's page table
     pub fn get_user_token(&self) -> usize {
         let inner = self.inner_exclusive_access();
-        inner.memory_set.token()
+        inner.memory_set.exclusive_access().token()
     }
     /// Trivial getter for priority.
     pub fn get_priority(&self) -> usize {
         self.inner.ro_access().sched_info.get_priority()
     }
+    /// This task's thread id: the `TaskUserRes`-assigned tid if it's one of
+    /// several threads sharing its process's address space (see
+    /// `thread_create`), or the pid itself for an ordinary single-threaded
+    /// task, mirroring `fs::procfs::render_status`'s `res.as_ref().map_or`.
+    pub fn gettid(&self) -> usize {
+        self.inner
+            .ro_access()
+            .res
+            .as_ref()
+            .map_or(self.pid.0, |res| res.tid)
+    }
 }
 
 /// Inner TCB, which contains inner mutability in a readonly TCB reference.
@@ -61,8 +81,11 @@ pub struct TaskControlBlockInner {
     /// Maintain the execution status of the current process
     pub task_status: TaskStatus,
 
-    /// Application address space
-    pub memory_set: MemorySet,
+    /// Application address space. Shared (via `Arc`) by every thread of one
+    /// process: `thread_create` clones this handle instead of copying the
+    /// `MemorySet`, so writes any thread makes -- including `sbrk` -- are
+    /// visible to its sibling threads.
+    pub memory_set: Arc<UPSafeCell<MemorySet>>,
 
     /// Parent process of the current process.
     /// Weak will not affect the reference count of the parent
@@ -85,6 +108,42 @@ pub struct TaskControlBlockInner {
 
     /// Task scheduling infomation
     pub sched_info: SchedInfo,
+
+    /// This task's thread identity within its process, if it is one thread
+    /// among several sharing this process's address space. `None` for a
+    /// task that is the sole thread of its process.
+    pub res: Option<TaskUserRes>,
+
+    /// Allocates/recycles tids for this process's threads. Shared via `Arc`
+    /// the same way `memory_set` is, since tids must be unique across all
+    /// threads sharing one address space, not just within the
+    /// `TaskControlBlock` that happened to create a given thread.
+    pub tid_allocator: Arc<UPSafeCell<RecycleAllocator>>,
+
+    /// Seccomp-style policy consulted on every syscall
+    pub syscall_filter: SyscallFilter,
+
+    /// Pending/blocked signals and installed handlers for this task
+    pub signals: SignalState,
+
+    /// The tracer currently attached to this task via `PTRACE_ATTACH` or
+    /// `PTRACE_TRACEME`, if any. A `Weak` so a tracer exiting first doesn't
+    /// keep this task pinned.
+    pub tracer: Option<Weak<TaskControlBlock>>,
+
+    /// `mmap` regions reserved in this task's address space but not yet
+    /// backed by frames, see `mm::utils::mmap_handle`. Each region also
+    /// carries its own `mlock`-against-reclaim ("wired") flag, see
+    /// `mm::utils::mmap_handle::do_mlock`.
+    pub lazy_mmap_regions: Vec<LazyMmapRegion>,
+
+    /// Priorities currently donated to this task by waiters blocked on
+    /// mutexes it holds, keyed by the donating mutex's `resource_id`. A
+    /// per-task record (rather than per-mutex) so that releasing one held
+    /// donated-priority mutex only withdraws *that* mutex's donation,
+    /// instead of clobbering a boost still owed by another mutex this task
+    /// also holds. See `recompute_priority` and `sync::mutex::MutexBlocking`.
+    pub priority_donations: Vec<(usize, usize)>,
 }
 
 impl TaskControlBlockInner {
@@ -94,7 +153,7 @@ impl TaskControlBlockInner {
     }
     /// get the user token
     pub fn get_user_token(&self) -> usize {
-        self.memory_set.token()
+        self.memory_set.exclusive_access().token()
     }
     fn get_status(&self) -> TaskStatus {
         self.task_status
@@ -122,6 +181,274 @@ impl TaskControlBlockInner {
         self.task_status = task_status;
         self.exit_code   = exit_code;
     }
+
+    /// Re-derive this task's effective scheduling priority as the max of
+    /// its own (undonated) priority and every priority currently donated to
+    /// it by a mutex it holds, applying the result through
+    /// `SchedInfo::set_priority` (which leaves `_stride` untouched, so
+    /// fairness accounting survives the boost).
+    pub fn recompute_priority(&mut self) {
+        let boosted = self
+            .priority_donations
+            .iter()
+            .map(|(_, priority)| *priority)
+            .fold(self.sched_info.get_base_priority(), usize::max);
+        self.sched_info.set_priority(boosted);
+    }
+
+    /// Record a syscall in the statistics counters, then consult this
+    /// task's seccomp-style filter for what to do about it.
+    ///
+    /// `trap::trap_handler` (outside this module, and not part of this
+    /// source tree -- there is no `trap/` directory here to edit) must call
+    /// this right before it dispatches each syscall, and act on the result:
+    /// `Allow` lets the dispatch proceed, `Errno(code)` returns `code`
+    /// without dispatching, `Kill` exits the task instead of dispatching.
+    /// Until that call is added, this is unreachable, `SyscallFilter`
+    /// policies installed by `sys_set_syscall_filter` are never enforced,
+    /// and `sys_task_info`'s `syscall_times` (which reads `statistics`
+    /// populated only through this method) stays all-zero.
+    pub fn check_syscall(&mut self, syscall_id: usize) -> SyscallAction {
+        self.statistics.on_syscall(syscall_id);
+        self.syscall_filter.action_for(syscall_id)
+    }
+
+    /// Dispatch the next pending, unblocked signal for this task, if any.
+    /// Returns `Some(exit_code)` when a default-fatal signal had no
+    /// installed handler, in which case the caller should call
+    /// `exit_current_and_run_next(exit_code)` instead of resuming user
+    /// mode -- which, if a tracer is attached, stops this task for
+    /// inspection one last time via `stop_for_tracer` before it becomes a
+    /// zombie. Otherwise the current `TrapContext` has already been backed
+    /// up and redirected to the handler (if a signal was dispatched) or
+    /// left untouched (if nothing was pending).
+    ///
+    /// Callers should invoke this right before `trap_return` restores the
+    /// user trap context.
+    pub fn check_pending_signals(&mut self) -> Option<i32> {
+        match self.signals.next_dispatch()? {
+            Ok(exit_code) => Some(exit_code),
+            Err((signum, action)) => {
+                let trap_cx = self.get_trap_cx();
+                self.signals.trap_ctx_backup = Some(*trap_cx);
+                self.signals.handling_sig = Some(signum);
+                self.signals.mask |= action.mask;
+                let trap_cx = self.get_trap_cx();
+                trap_cx.sepc = action.handler;
+                trap_cx.x[10] = signum;
+                None
+            }
+        }
+    }
+
+    /// `sys_sigreturn`: restore the trap context saved before the
+    /// currently-running handler was dispatched, undoing the temporary
+    /// mask widening. Returns `-1` if no handler is running.
+    pub fn sigreturn(&mut self) -> isize {
+        match self.signals.trap_ctx_backup.take() {
+            Some(backup) => {
+                *self.get_trap_cx() = backup;
+                self.signals.handling_sig = None;
+                0
+            }
+            None => -1,
+        }
+    }
+
+    /// If this task is being traced, switch it to `TraceStopped` and wake
+    /// its tracer so a `sys_ptrace(PTRACE_CONT/SINGLESTEP, ...)` call (or
+    /// the tracer's own wait loop) can observe the stop. Returns whether a
+    /// live tracer was found; callers should fall back to their normal
+    /// behaviour (run the default signal action, exit, ...) when it wasn't.
+    ///
+    /// Callers should invoke this wherever a traced task would otherwise
+    /// act on a default-fatal signal or exit, right alongside
+    /// `check_pending_signals`/`on_dead`.
+    pub fn stop_for_tracer(&mut self) -> bool {
+        let Some(tracer) = self.tracer.as_ref().and_then(Weak::upgrade) else {
+            return false;
+        };
+        self.task_status = TaskStatus::TraceStopped;
+        super::wakeup_task(tracer);
+        true
+    }
+}
+
+/// Exit code a task is given when `on_dead` is invoked because its syscall
+/// filter said `Kill`
+pub const SECCOMP_KILL_EXIT_CODE: i32 = -31;
+
+/// What to do when a task makes a particular syscall
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SyscallAction {
+    /// Let the syscall run normally
+    Allow,
+    /// Skip the syscall and return `code` to the caller instead of running it
+    Errno(i32),
+    /// Skip the syscall and kill the task instead of running it
+    Kill,
+}
+
+impl SyscallAction {
+    const ENCODED_ALLOW: u8 = 0;
+    const ENCODED_KILL: u8 = u8::MAX;
+
+    /// Pack this action into the single byte `SyscallFilter` stores per
+    /// syscall id. `Errno` codes are clamped to what a byte can hold.
+    fn encode(self) -> u8 {
+        match self {
+            SyscallAction::Allow => Self::ENCODED_ALLOW,
+            SyscallAction::Kill => Self::ENCODED_KILL,
+            SyscallAction::Errno(code) => {
+                (code.unsigned_abs() as u8).clamp(1, Self::ENCODED_KILL - 1)
+            }
+        }
+    }
+
+    fn decode(byte: u8) -> Self {
+        match byte {
+            Self::ENCODED_ALLOW => SyscallAction::Allow,
+            Self::ENCODED_KILL => SyscallAction::Kill,
+            code => SyscallAction::Errno(-(code as i32)),
+        }
+    }
+}
+
+/// A per-task seccomp-style policy: one action per syscall id, default
+/// `Allow`, that can be locked so a sandboxed task can't widen its own
+/// rights once a stricter policy has been installed
+#[derive(Clone, Copy)]
+pub struct SyscallFilter {
+    actions: [u8; MAX_SYSCALL_NUM],
+    locked: bool,
+}
+
+impl SyscallFilter {
+    /// A filter that allows everything and is not yet locked
+    pub fn new() -> Self {
+        Self {
+            actions: [SyscallAction::ENCODED_ALLOW; MAX_SYSCALL_NUM],
+            locked: false,
+        }
+    }
+
+    /// What action this filter prescribes for `syscall_id`
+    pub fn action_for(&self, syscall_id: usize) -> SyscallAction {
+        match self.actions.get(syscall_id) {
+            Some(&byte) => SyscallAction::decode(byte),
+            None => SyscallAction::Allow,
+        }
+    }
+
+    /// Install `action` for `syscall_id`. Fails once this filter is locked,
+    /// so a task can only ever narrow its own policy.
+    pub fn set_action(&mut self, syscall_id: usize, action: SyscallAction) -> bool {
+        if self.locked || syscall_id >= MAX_SYSCALL_NUM {
+            return false;
+        }
+        self.actions[syscall_id] = action.encode();
+        true
+    }
+
+    /// Make this filter immutable: no further `set_action` calls will succeed
+    pub fn lock(&mut self) {
+        self.locked = true;
+    }
+
+    /// Whether this filter has been locked
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+}
+
+/// Hands out small integer ids, reusing ids that have been freed before
+/// minting new ones
+pub struct RecycleAllocator {
+    current: usize,
+    recycled: Vec<usize>,
+}
+
+impl RecycleAllocator {
+    /// Create an allocator with nothing handed out yet
+    pub fn new() -> Self {
+        Self {
+            current: 0,
+            recycled: Vec::new(),
+        }
+    }
+
+    /// Hand out an id
+    pub fn alloc(&mut self) -> usize {
+        if let Some(id) = self.recycled.pop() {
+            id
+        } else {
+            self.current += 1;
+            self.current - 1
+        }
+    }
+
+    /// Return `id` so a future `alloc` can reuse it
+    pub fn dealloc(&mut self, id: usize) {
+        assert!(id < self.current);
+        assert!(
+            !self.recycled.iter().any(|recycled_id| *recycled_id == id),
+            "id {} has been deallocated!",
+            id
+        );
+        self.recycled.push(id);
+    }
+}
+
+/// Per-thread user-space resources: a tid, and the user stack and trap
+/// context derived from it that live in the owning process's address space
+pub struct TaskUserRes {
+    /// This thread's id within its process
+    pub tid: usize,
+    /// Base virtual address of this thread's user stack, before offsetting
+    /// by `tid`
+    pub ustack_base: usize,
+    /// The process (the first `TaskControlBlock` created for it, which owns
+    /// the shared address space and the tid allocator) this thread belongs to
+    pub process: Weak<TaskControlBlock>,
+}
+
+impl TaskUserRes {
+    /// Allocate a tid from `process` and derive this thread's stack/trap-cx
+    /// addresses from it
+    pub fn new(process: &Arc<TaskControlBlock>, ustack_base: usize) -> Self {
+        let tid_allocator = process.inner_exclusive_access().tid_allocator.clone();
+        let tid = tid_allocator.exclusive_access().alloc();
+        Self {
+            tid,
+            ustack_base,
+            process: Arc::downgrade(process),
+        }
+    }
+
+    /// Return this thread's tid to its process's tid allocator
+    pub fn dealloc_tid(&self) {
+        if let Some(process) = self.process.upgrade() {
+            let tid_allocator = process.inner_exclusive_access().tid_allocator.clone();
+            tid_allocator.exclusive_access().dealloc(self.tid);
+        }
+    }
+
+    /// Base virtual address of this thread's user stack
+    pub fn ustack_base(&self) -> usize {
+        self.ustack_base + self.tid * THREAD_USTACK_STRIDE
+    }
+
+    /// Virtual address of this thread's trap context page, stacked below
+    /// the trampoline/trap-context page shared by thread 0
+    pub fn trap_cx_user_va(&self) -> usize {
+        TRAP_CONTEXT_BASE - self.tid * PAGE_SIZE
+    }
+}
+
+impl Drop for TaskUserRes {
+    fn drop(&mut self) {
+        self.dealloc_tid();
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -140,17 +467,43 @@ pub struct TcbStatistics {
     pub last_deactivate_time: usize,
 }
 
+/// Which algorithm a task is scheduled under, as set by
+/// `sys_sched_setscheduler`. The value is only stored: `_stride` advances
+/// the same way under every policy, and the scheduler does not apply the
+/// `Fifo`/`RoundRobin` behaviour described on the variants.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SchedulerPolicy {
+    /// Stride-scheduled: priority governs its pass, runs interleaved with
+    /// every other `Normal` task
+    Normal,
+    /// Runs until it blocks or yields; never preempted by a timer tick
+    Fifo,
+    /// Like `Fifo`, but re-queued behind other `RoundRobin` tasks on a
+    /// timer tick instead of keeping the CPU
+    RoundRobin,
+}
+
 #[derive(Clone, Copy)]
 /// Data related to stride scheduling algorithm
 pub struct SchedInfo {
-    /// Priority
+    /// Priority. This is the *effective* priority actually fed into
+    /// `_pass`/`_stride` -- may sit above `_base_priority` while a mutex is
+    /// donating to this task (see `TaskControlBlockInner::recompute_priority`).
     _priority: usize,
 
+    /// This task's own priority, absent any priority-inheritance boost.
+    /// What `set_own_priority`/`sys_setpriority` actually set; `_priority`
+    /// is re-derived from this plus any active donations.
+    _base_priority: usize,
+
     /// Pass (equals to STRIDE_BASE / priority)
     _pass:     usize,
 
     /// Current stride
     _stride:   usize,
+
+    /// Scheduling policy; `update` advances `_stride` under every policy
+    _policy: SchedulerPolicy,
 }
 
 /// Stride object, which lives in ready queue.
@@ -167,12 +520,23 @@ impl SchedInfo {
     /// default pass, like DEFAULT_BIG_STRIDE / DEFAULT_PRIORITY.
     pub const DEFAULT_PASS: usize = Self::DEFAULT_BIG_STRIDE / Self::DEFAULT_PRIORITY;
 
+    /// Lowest priority value accepted by `set_priority`, i.e. the lowest
+    /// allowed priority (the largest `_pass`)
+    pub const MIN_PRIORITY: usize = 2;
+
+    /// Highest priority value accepted by `set_priority`, i.e. the highest
+    /// allowed priority: clamps how small `_pass` can get so one task
+    /// can't starve the rest of the stride-scheduled pool
+    pub const MAX_PRIORITY: usize = 1000;
+
     /// New SchedInfo instance for new process.
     pub fn new()-> Self {
         Self {
             _priority: Self::DEFAULT_PRIORITY,
+            _base_priority: Self::DEFAULT_PRIORITY,
             _pass:     Self::DEFAULT_PASS,
             _stride: 0,
+            _policy: SchedulerPolicy::Normal,
         }
     }
 
@@ -180,6 +544,7 @@ impl SchedInfo {
     pub fn with_priority(prio: usize)-> Self {
         Self {
             _priority: prio,
+            _base_priority: prio,
             // Most of prioroties are running in DEFAULT_PRIORITY,
             // use this selection to decrease dividing
             _pass: if prio == Self::DEFAULT_PRIORITY {
@@ -187,7 +552,8 @@ impl SchedInfo {
                 } else {
                     Self::DEFAULT_BIG_STRIDE / prio
                 },
-            _stride: 0
+            _stride: 0,
+            _policy: SchedulerPolicy::Normal,
         }
     }
 
@@ -195,8 +561,10 @@ impl SchedInfo {
     pub fn clone_from(old_sched_info: &Self)-> Self {
         Self {
             _priority: old_sched_info._priority,
+            _base_priority: old_sched_info._base_priority,
             _pass:     old_sched_info._pass,
-            _stride:   0
+            _stride:   0,
+            _policy:   old_sched_info._policy,
         }
     }
 
@@ -219,8 +587,11 @@ impl SchedInfo {
     pub fn get_priority(&self)-> usize { self._priority }
     /// Setter: priority
     ///
-    /// This updates `_pass` field
+    /// Clamped to `[MIN_PRIORITY, MAX_PRIORITY]` so a single high-priority
+    /// task can't push `_pass` low enough to starve everyone else. This
+    /// updates `_pass` field.
     pub fn set_priority(&mut self, priority: usize)-> &mut Self {
+        let priority = priority.clamp(Self::MIN_PRIORITY, Self::MAX_PRIORITY);
         self._priority = priority;
         self._pass = match priority {
             Self::DEFAULT_PRIORITY => Self::DEFAULT_PASS,
@@ -229,6 +600,25 @@ impl SchedInfo {
         self
     }
 
+    /// Trivial getter: base (undonated) priority
+    pub fn get_base_priority(&self)-> usize { self._base_priority }
+    /// Setter: base priority, e.g. from `sys_setpriority`. Does not by
+    /// itself touch the effective `_priority`/`_pass` -- callers update
+    /// those afterwards via `TaskControlBlockInner::recompute_priority` so
+    /// an active mutex donation isn't lost.
+    pub fn set_own_priority(&mut self, priority: usize)-> &mut Self {
+        self._base_priority = priority.clamp(Self::MIN_PRIORITY, Self::MAX_PRIORITY);
+        self
+    }
+
+    /// Trivial getter: scheduling policy
+    pub fn get_policy(&self)-> SchedulerPolicy { self._policy }
+    /// Setter: scheduling policy
+    pub fn set_policy(&mut self, policy: SchedulerPolicy)-> &mut Self {
+        self._policy = policy;
+        self
+    }
+
     /// Update schedule infomation on process run
     pub fn update(&mut self, dtime: usize)-> &mut Self {
         self._stride += self._pass as usize * dtime;
@@ -332,7 +722,7 @@ impl TaskControlBlock {
             .unwrap()
             .ppn();
         // alloc a pid and a kernel stack in kernel space
-        let pid_handle = pid_alloc();
+        let pid_handle = Arc::new(pid_alloc());
         let kernel_stack = kstack_alloc();
         let kernel_stack_top = kernel_stack.get_top();
         // push a task context which goes to trap_return to the top of kernel stack
@@ -345,7 +735,7 @@ impl TaskControlBlock {
                     base_size: user_sp,
                     task_cx: TaskContext::goto_trap_return(kernel_stack_top),
                     task_status: TaskStatus::Ready,
-                    memory_set,
+                    memory_set: Arc::new(UPSafeCell::new(memory_set)),
                     parent: None,
                     children: Vec::new(),
                     exit_code: 0,
@@ -353,6 +743,13 @@ impl TaskControlBlock {
                     program_brk: user_sp,
                     statistics:  TcbStatistics::empty(),
                     sched_info:  SchedInfo::new(),
+                    res: None,
+                    tid_allocator: Arc::new(UPSafeCell::new(RecycleAllocator::new())),
+                    syscall_filter: SyscallFilter::new(),
+                    signals: SignalState::default(),
+                    tracer: None,
+                    lazy_mmap_regions: Vec::new(),
+                    priority_donations: Vec::new(),
                 })
             },
         };
@@ -379,8 +776,10 @@ impl TaskControlBlock {
 
         // **** access current TCB exclusively
         let mut inner = self.inner_exclusive_access();
-        // substitute memory_set
-        inner.memory_set = memory_set;
+        // substitute memory_set contents in place, rather than the `Arc`
+        // itself, so this still lands correctly if another thread somehow
+        // held a clone of the old handle
+        *inner.memory_set.exclusive_access() = memory_set;
         // update trap_cx ppn
         inner.trap_cx_ppn = trap_cx_ppn;
         // initialize base_size
@@ -389,6 +788,10 @@ impl TaskControlBlock {
         inner.statistics.on_exec();
         // reset schedule data
         inner.sched_info.full_reset();
+        // a freshly exec'd program gets a clean, unlocked filter
+        inner.syscall_filter = SyscallFilter::new();
+        // a freshly exec'd program gets default signal dispositions
+        inner.signals = SignalState::default();
         // initialize trap_cx
         let trap_cx = inner.get_trap_cx();
         *trap_cx = TrapContext::app_init_context(
@@ -405,14 +808,16 @@ impl TaskControlBlock {
     pub fn fork(self: &Arc<Self>) -> Arc<Self> {
         // ---- access parent PCB exclusively
         let mut parent_inner = self.inner_exclusive_access();
-        // copy user space(include trap context)
-        let memory_set = MemorySet::from_existed_user(&parent_inner.memory_set);
+        // copy user space(include trap context) -- a real copy, unlike
+        // thread_create's shared Arc, since a forked child gets its own
+        // address space
+        let memory_set = MemorySet::from_existed_user(&parent_inner.memory_set.exclusive_access());
         let trap_cx_ppn = memory_set
             .translate(VirtAddr::from(TRAP_CONTEXT_BASE).into())
             .unwrap()
             .ppn();
         // alloc a pid and a kernel stack in kernel space
-        let pid_handle = pid_alloc();
+        let pid_handle = Arc::new(pid_alloc());
         let kernel_stack = kstack_alloc();
         let kernel_stack_top = kernel_stack.get_top();
         let task_control_block = Arc::new(TaskControlBlock {
@@ -424,7 +829,7 @@ impl TaskControlBlock {
                     base_size: parent_inner.base_size,
                     task_cx: TaskContext::goto_trap_return(kernel_stack_top),
                     task_status: TaskStatus::Ready,
-                    memory_set,
+                    memory_set: Arc::new(UPSafeCell::new(memory_set)),
                     parent: Some(Arc::downgrade(self)),
                     children: Vec::new(),
                     exit_code: 0,
@@ -432,6 +837,13 @@ impl TaskControlBlock {
                     program_brk: parent_inner.program_brk,
                     statistics:  TcbStatistics::empty(),
                     sched_info:  SchedInfo::clone_from(&parent_inner.sched_info),
+                    res: None,
+                    tid_allocator: Arc::new(UPSafeCell::new(RecycleAllocator::new())),
+                    syscall_filter: parent_inner.syscall_filter,
+                    signals: SignalState::default(),
+                    tracer: None,
+                    lazy_mmap_regions: Vec::new(),
+                    priority_donations: Vec::new(),
                 })
             },
         });
@@ -447,6 +859,89 @@ impl TaskControlBlock {
         // ---- release parent PCB
     }
 
+    /// Create a new thread sharing this task's process: the *same*
+    /// `memory_set` and pid (both `Arc`-cloned, not copied) plus a freshly
+    /// allocated tid, and scheduling info cloned from this task (so the new
+    /// thread starts at the default stride rather than inheriting this
+    /// task's progress). Because `memory_set` is shared, the stack/trap-cx
+    /// areas mapped in for this thread, and anything it later writes to the
+    /// heap or any other page, are visible to every other thread of the
+    /// same process.
+    pub fn thread_create(self: &Arc<Self>, entry: usize, arg: usize) -> Arc<Self> {
+        // ---- access this task (the process) exclusively
+        let mut process_inner = self.inner_exclusive_access();
+        let memory_set = process_inner.memory_set.clone();
+        let tid_allocator = process_inner.tid_allocator.clone();
+        let res = TaskUserRes {
+            tid: tid_allocator.exclusive_access().alloc(),
+            ustack_base: process_inner.heap_bottom,
+            process: Arc::downgrade(self),
+        };
+        let ustack_bottom = res.ustack_base();
+        let ustack_top = ustack_bottom + USER_STACK_SIZE;
+        memory_set.exclusive_access().insert_framed_area(
+            VirtAddr::from(ustack_bottom),
+            VirtAddr::from(ustack_top),
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        );
+        let trap_cx_va = res.trap_cx_user_va();
+        memory_set.exclusive_access().insert_framed_area(
+            VirtAddr::from(trap_cx_va),
+            VirtAddr::from(trap_cx_va + PAGE_SIZE),
+            MapPermission::R | MapPermission::W,
+        );
+        let trap_cx_ppn = memory_set
+            .exclusive_access()
+            .translate(VirtAddr::from(trap_cx_va).into())
+            .unwrap()
+            .ppn();
+        // share the process's pid rather than allocating a new one: this
+        // thread and its process are the same pid as far as userspace
+        // (getpid, /proc, signal delivery by pid, ...) is concerned
+        let pid_handle = self.pid.clone();
+        let kernel_stack = kstack_alloc();
+        let kernel_stack_top = kernel_stack.get_top();
+        let thread = Arc::new(TaskControlBlock {
+            pid: pid_handle,
+            kernel_stack,
+            inner: unsafe {
+                UPSafeCell::new(TaskControlBlockInner {
+                    trap_cx_ppn,
+                    base_size: process_inner.base_size,
+                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+                    task_status: TaskStatus::Ready,
+                    memory_set,
+                    parent: process_inner.parent.clone(),
+                    children: Vec::new(),
+                    exit_code: 0,
+                    heap_bottom: process_inner.heap_bottom,
+                    program_brk: process_inner.program_brk,
+                    statistics: TcbStatistics::empty(),
+                    sched_info: SchedInfo::clone_from(&process_inner.sched_info),
+                    res: Some(res),
+                    tid_allocator,
+                    syscall_filter: process_inner.syscall_filter,
+                    signals: SignalState::default(),
+                    tracer: None,
+                    lazy_mmap_regions: Vec::new(),
+                    priority_donations: Vec::new(),
+                })
+            },
+        });
+        let trap_cx = thread.inner_exclusive_access().get_trap_cx();
+        *trap_cx = TrapContext::app_init_context(
+            entry,
+            ustack_top,
+            KERNEL_SPACE.exclusive_access().token(),
+            kernel_stack_top,
+            trap_handler as usize,
+        );
+        trap_cx.x[10] = arg;
+        process_inner.children.push(thread.clone());
+        thread
+        // ---- release this task (the process)
+    }
+
     /// spawn a new process with elf data `app_elf`
     pub fn spawn(self: &Arc<Self>, app_elf: &[u8])-> Arc<Self> {
         let ret = Arc::new(Self::new(app_elf));
@@ -472,10 +967,12 @@ impl TaskControlBlock {
         let result = if size < 0 {
             inner
                 .memory_set
+                .exclusive_access()
                 .shrink_to(VirtAddr(heap_bottom), VirtAddr(new_brk as usize))
         } else {
             inner
                 .memory_set
+                .exclusive_access()
                 .append_to(VirtAddr(heap_bottom), VirtAddr(new_brk as usize))
         };
         if result {
@@ -509,7 +1006,7 @@ impl TaskControlBlock {
 }
 
 #[derive(Copy, Clone, PartialEq)]
-/// task status: UnInit, Ready, Running, Exited
+/// task status: UnInit, Ready, Running, Blocked, TraceStopped, Zombie
 pub enum TaskStatus {
     /// uninitialized
     UnInit,
@@ -517,6 +1014,12 @@ pub enum TaskStatus {
     Ready,
     /// running
     Running,
+    /// blocked on a mutex/semaphore/condvar/futex/timer, off the ready queue
+    /// until something wakes it back up
+    Blocked,
+    /// stopped for inspection by a `PTRACE_ATTACH`/`PTRACE_TRACEME` tracer;
+    /// resumes on `PTRACE_CONT`/`PTRACE_SINGLESTEP`
+    TraceStopped,
     /// exited
     Zombie,
 }